@@ -1,17 +1,30 @@
 // Build script to compile Protocol Buffer definitions
 
+use std::path::Path;
+
+const PROTO_DIR: &str = "../proto";
+const TELEMETRY_PROTO: &str = "../proto/telemetry.proto";
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Compile telemetry.proto
+    println!("cargo:rerun-if-changed={}", TELEMETRY_PROTO);
+
+    // Catch a missing checkout of proto/ here rather than as unresolved
+    // imports from an empty module
+    if !Path::new(TELEMETRY_PROTO).is_file() {
+        return Err(format!(
+            "{} not found (relative to {}); the agent needs the shared proto/ directory to build",
+            TELEMETRY_PROTO,
+            std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default()
+        )
+        .into());
+    }
+
+    // Compile telemetry.proto into OUT_DIR; src/proto.rs includes it
     tonic_build::configure()
-        .build_server(false) // Agent is client-only
+        // Server stubs ship too, for the in-process test ingestor used by the telemetry and pipeline tests
+        .build_server(true)
         .build_client(true)
-        .out_dir("src/generated")
-        .compile(
-            &["../proto/telemetry.proto"],
-            &["../proto"],
-        )?;
-
-    println!("cargo:rerun-if-changed=../proto/telemetry.proto");
+        .compile(&[TELEMETRY_PROTO], &[PROTO_DIR])?;
 
     Ok(())
 }
@@ -0,0 +1,498 @@
+// Linux authentication log collector
+// Tails the syslog authentication log (auth.log / secure) and emits an
+// Authentication event for every login attempt it can attribute.
+
+#![cfg(target_os = "linux")]
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::net::IpAddr;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::config::AgentConfig;
+use crate::health::{Collector, ComponentState, HEALTH};
+use crate::ratelimit::RateLimiter;
+use crate::telemetry::{Event, EventContext, EventEmitter, EventType};
+
+/// Checked in order when `auth_log_path` is not configured
+/// (Debian/Ubuntu, then RHEL/Fedora).
+const DEFAULT_AUTH_LOGS: &[&str] = &["/var/log/auth.log", "/var/log/secure"];
+
+/// How often the log is checked for new lines and rotation.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Successful logins use a valid account; failures count towards brute force.
+const SUCCESS_ATTACK: (&str, &str) = ("TA0001_Initial_Access", "T1078");
+const FAILURE_ATTACK: (&str, &str) = ("TA0006_Credential_Access", "T1110");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthOutcome {
+    Success,
+    Failure,
+}
+
+impl AuthOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthOutcome::Success => "success",
+            AuthOutcome::Failure => "failure",
+        }
+    }
+}
+
+/// One login attempt parsed from an auth log line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthRecord {
+    /// Program that logged the attempt (sshd, su, login, ...).
+    pub service: String,
+    pub pid: Option<u32>,
+    /// Account name as given by the client; may not exist (see `invalid_user`).
+    pub user: String,
+    pub invalid_user: bool,
+    pub source_ip: Option<IpAddr>,
+    pub source_port: Option<u16>,
+    pub tty: Option<String>,
+    /// Authentication method when logged (password, publickey, ...).
+    pub method: Option<String>,
+    pub outcome: AuthOutcome,
+    /// Time from the log line; only RFC 3339 timestamps carry enough
+    /// information (year and zone) to be converted.
+    pub timestamp_ms: Option<i64>,
+}
+
+/// Parse one syslog line. Returns `None` for anything that is not a
+/// login attempt.
+///
+/// Recognised messages:
+/// - sshd `Accepted <method> for <user> from <ip> port <port>`
+/// - sshd `Failed <method> for [invalid user ]<user> from <ip> port <port>`
+/// - PAM `authentication failure; ... tty=<tty> ... rhost=<host> user=<user>`
+///   from services other than sshd (which logs its own Failed line)
+pub fn parse_auth_line(line: &str) -> Option<AuthRecord> {
+    let (timestamp_ms, rest) = split_timestamp(line)?;
+    let (_host, rest) = rest.trim_start().split_once(' ')?;
+    let (tag, message) = rest.split_once(": ")?;
+
+    let (service, pid) = match tag.split_once('[') {
+        Some((name, pid)) => (name, pid.trim_end_matches(']').parse().ok()),
+        None => (tag, None),
+    };
+
+    let mut record = if service == "sshd" {
+        parse_sshd(message)?
+    } else {
+        parse_pam_failure(message)?
+    };
+    record.service = service.to_string();
+    record.pid = pid;
+    record.timestamp_ms = timestamp_ms;
+    Some(record)
+}
+
+fn parse_sshd(message: &str) -> Option<AuthRecord> {
+    let (outcome, rest) = if let Some(rest) = message.strip_prefix("Accepted ") {
+        (AuthOutcome::Success, rest)
+    } else if let Some(rest) = message.strip_prefix("Failed ") {
+        (AuthOutcome::Failure, rest)
+    } else {
+        return None;
+    };
+
+    let (method, rest) = rest.split_once(" for ")?;
+    let (invalid_user, rest) = match rest.strip_prefix("invalid user ") {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let (user, rest) = rest.rsplit_once(" from ")?;
+    let mut words = rest.split_whitespace();
+    let source_ip = words.next()?.parse().ok()?;
+    let source_port = match (words.next(), words.next()) {
+        (Some("port"), Some(port)) => port.parse().ok(),
+        _ => None,
+    };
+
+    Some(AuthRecord {
+        service: String::new(),
+        pid: None,
+        user: user.to_string(),
+        invalid_user,
+        source_ip: Some(source_ip),
+        source_port,
+        tty: None,
+        method: Some(method.to_string()),
+        outcome,
+        timestamp_ms: None,
+    })
+}
+
+fn parse_pam_failure(message: &str) -> Option<AuthRecord> {
+    let (_, fields) = message.split_once("authentication failure;")?;
+
+    let field = |key: &str| {
+        fields
+            .split_whitespace()
+            .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+            .filter(|value| !value.is_empty())
+    };
+
+    Some(AuthRecord {
+        service: String::new(),
+        pid: None,
+        user: field("user").or_else(|| field("ruser")).unwrap_or_default().to_string(),
+        invalid_user: false,
+        source_ip: field("rhost").and_then(|host| host.parse().ok()),
+        source_port: None,
+        tty: field("tty").map(str::to_string),
+        method: None,
+        outcome: AuthOutcome::Failure,
+        timestamp_ms: None,
+    })
+}
+
+/// Split the syslog timestamp off `line`. RFC 3339 timestamps are
+/// converted; the classic `Oct 14 09:12:01` form has no year or zone and is
+/// skipped without a value.
+fn split_timestamp(line: &str) -> Option<(Option<i64>, &str)> {
+    let (first, rest) = line.split_once(' ')?;
+    if first.contains('T') && first.contains('-') {
+        return Some((parse_rfc3339_ms(first), rest));
+    }
+
+    // Month, day (space-padded below 10) and time
+    let rest = rest.trim_start();
+    let (_day, rest) = rest.split_once(' ')?;
+    let (_time, rest) = rest.split_once(' ')?;
+    Some((None, rest))
+}
+
+/// Parse `2024-10-14T09:12:01.123456+02:00` (or `Z`) into Unix milliseconds.
+fn parse_rfc3339_ms(s: &str) -> Option<i64> {
+    let (date, time) = s.split_once('T')?;
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+
+    // Zone: Z, +hh:mm or -hh:mm after the clock time
+    let (clock, offset_secs) = match time.find(['Z', '+', '-']) {
+        Some(i) if &time[i..] == "Z" => (&time[..i], 0),
+        Some(i) => {
+            let sign = if time.as_bytes()[i] == b'-' { -1 } else { 1 };
+            let (hours, minutes) = time[i + 1..].split_once(':')?;
+            (&time[..i], sign * (hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60))
+        }
+        None => return None,
+    };
+
+    let (hms, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    let mut hms_parts = hms.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (hms_parts.next()??, hms_parts.next()??, hms_parts.next()??);
+    let millis = format!("{:0<3}", fraction).get(..3)?.parse::<i64>().ok()?;
+
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86_400 + hour * 3600 + minute * 60 + second - offset_secs;
+    Some(secs * 1000 + millis)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Build the telemetry event for a parsed login attempt.
+pub fn auth_event(context: &EventContext, record: &AuthRecord) -> Event {
+    let payload = serde_json::json!({
+        "service": record.service,
+        "pid": record.pid,
+        "user": record.user,
+        "invalid_user": record.invalid_user,
+        "source_ip": record.source_ip.map(|ip| ip.to_string()),
+        "source_port": record.source_port,
+        "tty": record.tty,
+        "method": record.method,
+        "outcome": record.outcome.as_str(),
+    });
+
+    let (tactic, technique) = match record.outcome {
+        AuthOutcome::Success => SUCCESS_ATTACK,
+        AuthOutcome::Failure => FAILURE_ATTACK,
+    };
+
+    let mut event = Event::new(context, EventType::Authentication, tactic.to_string(), payload.to_string());
+    event.mitre_technique = technique.to_string();
+    if let Some(timestamp_ms) = record.timestamp_ms {
+        event.timestamp = timestamp_ms;
+    }
+    event
+}
+
+/// Follows an auth log from its current end, across logrotate.
+pub struct AuthLogCollector {
+    path: PathBuf,
+    events: EventEmitter,
+    event_context: EventContext,
+    file: Option<File>,
+    /// Inode of the open file, to notice when logrotate replaces it.
+    inode: u64,
+    position: u64,
+    /// Incomplete last line carried until its newline arrives.
+    partial: Vec<u8>,
+}
+
+impl AuthLogCollector {
+    pub fn new(path: PathBuf, event_tx: mpsc::Sender<Event>, config: &AgentConfig) -> Self {
+        Self {
+            path,
+            events: EventEmitter::new(event_tx, "auth log"),
+            event_context: EventContext::from_config(config),
+            file: None,
+            inode: 0,
+            position: 0,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Sample emitted events through the agent-wide `limiter`.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.events = self.events.with_rate_limiter(limiter);
+        self
+    }
+
+    /// Start following the log. Existing contents are skipped; only
+    /// attempts logged from now on are reported.
+    pub fn open_at_end(&mut self) -> Result<()> {
+        let mut file = File::open(&self.path)
+            .with_context(|| format!("Failed to open auth log {}", self.path.display()))?;
+        self.position = file.seek(SeekFrom::End(0))?;
+        self.inode = file.metadata()?.ino();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Read lines appended since the last poll and emit their events.
+    /// Returns the number of events emitted.
+    pub fn poll(&mut self) -> Result<usize> {
+        self.follow_rotation()?;
+        let Some(file) = self.file.as_mut() else {
+            return Ok(0);
+        };
+
+        let mut appended = Vec::new();
+        file.seek(SeekFrom::Start(self.position))?;
+        self.position += file.read_to_end(&mut appended)? as u64;
+        self.partial.extend_from_slice(&appended);
+
+        let Some(last_newline) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(0);
+        };
+        let complete: Vec<u8> = self.partial.drain(..=last_newline).collect();
+
+        let mut emitted = 0;
+        for line in String::from_utf8_lossy(&complete).lines() {
+            if let Some(record) = parse_auth_line(line) {
+                debug!("Auth {} for {} via {}", record.outcome.as_str(), record.user, record.service);
+                self.events.emit_event(auth_event(&self.event_context, &record));
+                emitted += 1;
+            }
+        }
+        Ok(emitted)
+    }
+
+    /// Reopen from the start when the path now names a new file
+    /// (rotated) or the file shrank (truncated).
+    fn follow_rotation(&mut self) -> Result<()> {
+        let metadata = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Between logrotate's rename and the new file appearing
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to stat auth log {}", self.path.display())),
+        };
+
+        if self.file.is_none() || metadata.ino() != self.inode || metadata.len() < self.position {
+            let file = File::open(&self.path)
+                .with_context(|| format!("Failed to reopen auth log {}", self.path.display()))?;
+            debug!("Auth log {} rotated, reading new file from the start", self.path.display());
+            self.inode = metadata.ino();
+            self.position = 0;
+            self.partial.clear();
+            self.file = Some(file);
+        }
+        Ok(())
+    }
+}
+
+/// The configured auth log, or the first default that exists.
+fn resolve_auth_log(config: &AgentConfig) -> Option<PathBuf> {
+    config.auth_log_path.clone().or_else(|| {
+        DEFAULT_AUTH_LOGS.iter().map(PathBuf::from).find(|path| Path::new(path).exists())
+    })
+}
+
+/// Entry point called from main.rs. Polls the auth log until `shutdown`
+/// is cancelled; returns immediately if the host has no auth log.
+pub async fn start_auth_collector(
+    event_tx: mpsc::Sender<Event>,
+    config: AgentConfig,
+    rate_limiter: Arc<RateLimiter>,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let Some(path) = resolve_auth_log(&config) else {
+        info!("No authentication log found, login monitoring disabled");
+        return Ok(());
+    };
+
+    HEALTH.set_collector(Collector::AuthLog, ComponentState::Starting);
+    let mut collector = AuthLogCollector::new(path, event_tx, &config).with_rate_limiter(rate_limiter);
+    if let Err(e) = collector.open_at_end() {
+        HEALTH.set_collector(Collector::AuthLog, ComponentState::Failed);
+        return Err(e);
+    }
+    info!("Monitoring logins via {}", collector.path.display());
+    HEALTH.set_collector(Collector::AuthLog, ComponentState::Running);
+
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                HEALTH.set_collector(Collector::AuthLog, ComponentState::Stopped);
+                return Ok(());
+            }
+            _ = interval.tick() => {
+                if let Err(e) = collector.poll() {
+                    warn!("Failed to read auth log: {:#}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const ACCEPTED: &str =
+        "Oct 14 09:12:01 web-1 sshd[2231]: Accepted publickey for alice from 203.0.113.5 port 52211 ssh2: ED25519 SHA256:abc";
+    const FAILED: &str =
+        "2024-10-14T09:12:03.250000+02:00 web-1 sshd[2240]: Failed password for invalid user admin from 198.51.100.7 port 4022 ssh2";
+
+    #[test]
+    fn test_parse_sshd_success() {
+        let record = parse_auth_line(ACCEPTED).unwrap();
+        assert_eq!(record.service, "sshd");
+        assert_eq!(record.pid, Some(2231));
+        assert_eq!(record.user, "alice");
+        assert!(!record.invalid_user);
+        assert_eq!(record.source_ip, Some("203.0.113.5".parse().unwrap()));
+        assert_eq!(record.source_port, Some(52211));
+        assert_eq!(record.method.as_deref(), Some("publickey"));
+        assert_eq!(record.outcome, AuthOutcome::Success);
+        // Classic syslog timestamps carry no year
+        assert_eq!(record.timestamp_ms, None);
+    }
+
+    #[test]
+    fn test_parse_sshd_failure() {
+        let record = parse_auth_line(FAILED).unwrap();
+        assert_eq!(record.user, "admin");
+        assert!(record.invalid_user);
+        assert_eq!(record.source_ip, Some("198.51.100.7".parse().unwrap()));
+        assert_eq!(record.source_port, Some(4022));
+        assert_eq!(record.method.as_deref(), Some("password"));
+        assert_eq!(record.outcome, AuthOutcome::Failure);
+        // 07:12:03.250 UTC
+        assert_eq!(record.timestamp_ms, Some(1_728_889_923_250));
+    }
+
+    #[test]
+    fn test_parse_pam_failure() {
+        let line = "Oct  4 22:01:17 web-1 su[911]: pam_unix(su:auth): authentication failure; \
+                    logname=bob uid=1000 euid=0 tty=pts/0 ruser=bob rhost=  user=root";
+        let record = parse_auth_line(line).unwrap();
+        assert_eq!(record.service, "su");
+        assert_eq!(record.user, "root");
+        assert_eq!(record.tty.as_deref(), Some("pts/0"));
+        assert_eq!(record.source_ip, None);
+        assert_eq!(record.outcome, AuthOutcome::Failure);
+    }
+
+    #[test]
+    fn test_non_auth_lines_ignored() {
+        for line in [
+            "Oct 14 09:12:01 web-1 sshd[2231]: Connection closed by 203.0.113.5 port 52211 [preauth]",
+            "Oct 14 09:12:01 web-1 sshd[2231]: pam_unix(sshd:auth): authentication failure; rhost=198.51.100.7 user=root",
+            "Oct 14 09:17:01 web-1 CRON[3001]: pam_unix(cron:session): session opened for user root(uid=0) by (uid=0)",
+            "garbage",
+            "",
+        ] {
+            assert_eq!(parse_auth_line(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_rfc3339_timestamps() {
+        assert_eq!(parse_rfc3339_ms("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_rfc3339_ms("2000-03-01T00:00:00.5Z"), Some(951_868_800_500));
+        assert_eq!(parse_rfc3339_ms("2024-01-01T00:00:00-05:00"), Some(1_704_085_200_000));
+        assert_eq!(parse_rfc3339_ms("2024-01-01T00:00:00"), None);
+    }
+
+    #[test]
+    fn test_auth_event_fields() {
+        let context = EventContext::new("agent-1".to_string(), "tenant-1".to_string());
+
+        let event = auth_event(&context, &parse_auth_line(FAILED).unwrap());
+        assert_eq!(event.event_type, EventType::Authentication);
+        assert_eq!(event.mitre_tactic, "TA0006_Credential_Access");
+        assert_eq!(event.mitre_technique, "T1110");
+        assert_eq!(event.timestamp, 1_728_889_923_250);
+        assert_eq!(event.tenant_id, "tenant-1");
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["user"], "admin");
+        assert_eq!(payload["source_ip"], "198.51.100.7");
+        assert_eq!(payload["outcome"], "failure");
+
+        let event = auth_event(&context, &parse_auth_line(ACCEPTED).unwrap());
+        assert_eq!(event.mitre_technique, "T1078");
+    }
+
+    #[test]
+    fn test_collector_follows_appends_and_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auth.log");
+        std::fs::write(&path, format!("{}\n", ACCEPTED)).unwrap();
+
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let mut collector = AuthLogCollector::new(path.clone(), event_tx, &AgentConfig::default());
+        collector.open_at_end().unwrap();
+        // Lines present before startup are not replayed
+        assert_eq!(collector.poll().unwrap(), 0);
+
+        let mut log = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(log, "{}\n{}", FAILED, &ACCEPTED[..20]).unwrap();
+        assert_eq!(collector.poll().unwrap(), 1);
+        // The half-written line completes on the next poll
+        writeln!(log, "{}", &ACCEPTED[20..]).unwrap();
+        assert_eq!(collector.poll().unwrap(), 1);
+
+        // logrotate: rename away and start a fresh file
+        std::fs::rename(&path, dir.path().join("auth.log.1")).unwrap();
+        std::fs::write(&path, format!("{}\n", FAILED)).unwrap();
+        assert_eq!(collector.poll().unwrap(), 1);
+
+        let outcomes: Vec<String> = std::iter::from_fn(|| event_rx.try_recv().ok())
+            .map(|event| serde_json::from_str::<serde_json::Value>(&event.payload).unwrap()["outcome"].to_string())
+            .collect();
+        assert_eq!(outcomes, vec!["\"failure\"", "\"success\"", "\"failure\""]);
+    }
+}
@@ -1,10 +1,75 @@
 // Agent configuration management
 // Loads settings from environment variables or config file.
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tonic::transport::Uri;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::dlp::Severity;
+use crate::ratelimit::RateLimits;
+
+/// File inside `state_dir` holding the persisted agent_id.
+const AGENT_ID_FILE: &str = "agent_id";
+
+/// Whether the agent may act on detections or only report them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnforcementMode {
+    /// Observe only: every detection, even Critical, produces telemetry alone.
+    #[default]
+    Monitor,
+    /// Detections may additionally trigger blocking actions.
+    Enforce,
+}
+
+impl EnforcementMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnforcementMode::Monitor => "monitor",
+            EnforcementMode::Enforce => "enforce",
+        }
+    }
+}
+
+impl FromStr for EnforcementMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "monitor" => Ok(EnforcementMode::Monitor),
+            "enforce" => Ok(EnforcementMode::Enforce),
+            other => Err(anyhow!("Unknown enforcement mode: {} (expected monitor or enforce)", other)),
+        }
+    }
+}
+
+/// Log line format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines for interactive use.
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow!("Unknown log format: {} (expected text or json)", other)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
     /// Unique agent identifier (UUID v4)
@@ -24,42 +89,633 @@ pub struct AgentConfig {
 
     /// Maximum event buffer size (prevents memory exhaustion)
     pub max_buffer_size: usize,
+
+    /// Compiled eBPF object containing the process monitor (Linux only)
+    pub ebpf_object_path: PathBuf,
+
+    /// Port for the Prometheus `/metrics` and `/healthz` endpoints (0 disables them)
+    pub metrics_port: u16,
+
+    /// Monitor (report only) or Enforce (allow blocking actions)
+    pub enforcement_mode: EnforcementMode,
+
+    /// Directory for state kept across restarts (e.g. the generated agent_id)
+    pub state_dir: PathBuf,
+
+    /// Disk space for spooling events while the ingestor is unreachable
+    /// (kept under `state_dir`; 0 disables spooling)
+    pub spool_max_bytes: u64,
+
+    /// JSON threat-intel feed of bad IPs, CIDR blocks and domains (optional)
+    pub ioc_file: Option<PathBuf>,
+
+    /// JSON DLP policy to load fingerprints from (optional; re-read on reload)
+    pub dlp_policy: Option<PathBuf>,
+
+    /// Log output format (text or json)
+    pub log_format: LogFormat,
+
+    /// Use TLS for the ingestor connection (requires an https:// URL)
+    pub tls_enabled: bool,
+
+    /// PEM CA certificate used to verify the ingestor
+    pub tls_ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate and private key for mutual TLS (optional, set together)
+    pub tls_client_cert: Option<PathBuf>,
+    pub tls_client_key: Option<PathBuf>,
+
+    /// Syslog authentication log to follow for login events (Linux only;
+    /// defaults to /var/log/auth.log or /var/log/secure)
+    pub auth_log_path: Option<PathBuf>,
+
+    /// Per-event-type limits in events per second, shared by all collectors
+    /// (unlisted types are unlimited)
+    pub rate_limits: RateLimits,
+
+    /// Events below this severity are counted and discarded instead of sent
+    pub min_severity: Severity,
+
+    /// Synthetic activities per second from the mock collector (builds with
+    /// the `mock` feature only)
+    pub mock_event_rate: u32,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            agent_id: Uuid::new_v4().to_string(),
+            ingestor_url: "http://127.0.0.1:50051".to_string(),
+            tenant_id: "default".to_string(),
+            dlp_enabled: true,
+            batch_size: 100,
+            max_buffer_size: 10000,
+            ebpf_object_path: PathBuf::from("/opt/sentinel/bpf/process_monitor.o"),
+            metrics_port: 0,
+            enforcement_mode: EnforcementMode::Monitor,
+            state_dir: default_state_dir(),
+            spool_max_bytes: 0,
+            ioc_file: None,
+            dlp_policy: None,
+            log_format: LogFormat::Text,
+            tls_enabled: false,
+            tls_ca_cert: None,
+            tls_client_cert: None,
+            tls_client_key: None,
+            auth_log_path: None,
+            rate_limits: RateLimits::default(),
+            min_severity: Severity::Low,
+            mock_event_rate: 10,
+        }
+    }
+}
+
+/// Platform-appropriate location for persistent agent state.
+fn default_state_dir() -> PathBuf {
+    #[cfg(windows)]
+    {
+        let program_data = std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into());
+        PathBuf::from(program_data).join("Sentinel")
+    }
+    #[cfg(not(windows))]
+    {
+        PathBuf::from("/var/lib/sentinel")
+    }
+}
+
+/// Settings read from a config file. Every field is optional so a file can
+/// set only what it cares about; the rest comes from env vars or defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    agent_id: Option<String>,
+    ingestor_url: Option<String>,
+    tenant_id: Option<String>,
+    dlp_enabled: Option<bool>,
+    batch_size: Option<usize>,
+    max_buffer_size: Option<usize>,
+    ebpf_object_path: Option<PathBuf>,
+    metrics_port: Option<u16>,
+    enforcement_mode: Option<EnforcementMode>,
+    state_dir: Option<PathBuf>,
+    spool_max_bytes: Option<u64>,
+    ioc_file: Option<PathBuf>,
+    dlp_policy: Option<PathBuf>,
+    log_format: Option<LogFormat>,
+    tls_enabled: Option<bool>,
+    tls_ca_cert: Option<PathBuf>,
+    tls_client_cert: Option<PathBuf>,
+    tls_client_key: Option<PathBuf>,
+    auth_log_path: Option<PathBuf>,
+    rate_limits: Option<RateLimits>,
+    min_severity: Option<Severity>,
+    mock_event_rate: Option<u32>,
+}
+
+impl FileConfig {
+    /// Parse a `.json` file as JSON and anything else as TOML.
+    fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+        let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid JSON config file: {}", path.display()))
+        } else {
+            toml::from_str(&contents)
+                .with_context(|| format!("Invalid TOML config file: {}", path.display()))
+        }
+    }
 }
 
 impl AgentConfig {
     /// Load configuration from environment variables with defaults.
+    /// If `SENTINEL_CONFIG_FILE` names a TOML/JSON file it is read first;
+    /// env vars override file values, and defaults fill whatever neither sets.
+    /// A present but unparseable value is an error rather than a silent fallback.
+    ///
+    /// When no agent_id is configured, the one persisted in `state_dir` is
+    /// reused so a restarted agent keeps its identity; the first start
+    /// generates and stores it.
     pub fn load() -> Result<Self> {
-        let agent_id = std::env::var("SENTINEL_AGENT_ID")
-            .unwrap_or_else(|_| Uuid::new_v4().to_string());
-
-        let ingestor_url = std::env::var("SENTINEL_INGESTOR_URL")
-            .unwrap_or_else(|_| "http://127.0.0.1:50051".to_string());
-
-        let tenant_id = std::env::var("SENTINEL_TENANT_ID")
-            .unwrap_or_else(|_| "default".to_string());
-
-        let dlp_enabled = std::env::var("SENTINEL_DLP_ENABLED")
-            .unwrap_or_else(|_| "true".to_string())
-            .parse::<bool>()
-            .unwrap_or(true);
-
-        let batch_size = std::env::var("SENTINEL_BATCH_SIZE")
-            .unwrap_or_else(|_| "100".to_string())
-            .parse::<usize>()
-            .unwrap_or(100);
-
-        let max_buffer_size = std::env::var("SENTINEL_MAX_BUFFER")
-            .unwrap_or_else(|_| "10000".to_string())
-            .parse::<usize>()
-            .unwrap_or(10000);
-
-        Ok(Self {
-            agent_id,
-            ingestor_url,
-            tenant_id,
-            dlp_enabled,
-            batch_size,
-            max_buffer_size,
-        })
+        Self::load_with(|key| std::env::var(key).ok())
+    }
+
+    /// Load configuration from a TOML (or `.json`) file alone.
+    /// Fields the file omits take their default values; an omitted agent_id
+    /// is freshly generated and not persisted.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::resolve(FileConfig::read(path)?, |_| None, false)
+    }
+
+    /// Build configuration from an arbitrary variable source.
+    fn load_with<F>(var: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let file = match var("SENTINEL_CONFIG_FILE") {
+            Some(path) => FileConfig::read(Path::new(&path))?,
+            None => FileConfig::default(),
+        };
+
+        Self::resolve(file, var, true)
+    }
+
+    /// Merge sources with precedence env var > config file > default.
+    /// With `persist_agent_id`, a missing agent_id comes from the state file.
+    fn resolve<F>(file: FileConfig, var: F, persist_agent_id: bool) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let defaults = Self::default();
+        let configured_agent_id = var("SENTINEL_AGENT_ID").or(file.agent_id);
+
+        let mut config = Self {
+            agent_id: configured_agent_id.clone().unwrap_or(defaults.agent_id),
+            ingestor_url: var("SENTINEL_INGESTOR_URL")
+                .or(file.ingestor_url)
+                .unwrap_or(defaults.ingestor_url),
+            tenant_id: var("SENTINEL_TENANT_ID")
+                .or(file.tenant_id)
+                .unwrap_or(defaults.tenant_id),
+            dlp_enabled: parse_var(&var, "SENTINEL_DLP_ENABLED")?
+                .or(file.dlp_enabled)
+                .unwrap_or(defaults.dlp_enabled),
+            batch_size: parse_var(&var, "SENTINEL_BATCH_SIZE")?
+                .or(file.batch_size)
+                .unwrap_or(defaults.batch_size),
+            max_buffer_size: parse_var(&var, "SENTINEL_MAX_BUFFER")?
+                .or(file.max_buffer_size)
+                .unwrap_or(defaults.max_buffer_size),
+            ebpf_object_path: var("SENTINEL_EBPF_OBJECT")
+                .map(PathBuf::from)
+                .or(file.ebpf_object_path)
+                .unwrap_or(defaults.ebpf_object_path),
+            metrics_port: parse_var(&var, "SENTINEL_METRICS_PORT")?
+                .or(file.metrics_port)
+                .unwrap_or(defaults.metrics_port),
+            enforcement_mode: parse_var(&var, "SENTINEL_ENFORCEMENT_MODE")?
+                .or(file.enforcement_mode)
+                .unwrap_or(defaults.enforcement_mode),
+            state_dir: var("SENTINEL_STATE_DIR")
+                .map(PathBuf::from)
+                .or(file.state_dir)
+                .unwrap_or(defaults.state_dir),
+            spool_max_bytes: parse_var(&var, "SENTINEL_SPOOL_MAX_BYTES")?
+                .or(file.spool_max_bytes)
+                .unwrap_or(defaults.spool_max_bytes),
+            ioc_file: var("SENTINEL_IOC_FILE")
+                .map(PathBuf::from)
+                .or(file.ioc_file)
+                .or(defaults.ioc_file),
+            dlp_policy: var("SENTINEL_DLP_POLICY")
+                .map(PathBuf::from)
+                .or(file.dlp_policy)
+                .or(defaults.dlp_policy),
+            log_format: parse_var(&var, "SENTINEL_LOG_FORMAT")?
+                .or(file.log_format)
+                .unwrap_or(defaults.log_format),
+            tls_enabled: parse_var(&var, "SENTINEL_TLS_ENABLED")?
+                .or(file.tls_enabled)
+                .unwrap_or(defaults.tls_enabled),
+            tls_ca_cert: var("SENTINEL_TLS_CA_CERT")
+                .map(PathBuf::from)
+                .or(file.tls_ca_cert)
+                .or(defaults.tls_ca_cert),
+            tls_client_cert: var("SENTINEL_TLS_CLIENT_CERT")
+                .map(PathBuf::from)
+                .or(file.tls_client_cert)
+                .or(defaults.tls_client_cert),
+            tls_client_key: var("SENTINEL_TLS_CLIENT_KEY")
+                .map(PathBuf::from)
+                .or(file.tls_client_key)
+                .or(defaults.tls_client_key),
+            auth_log_path: var("SENTINEL_AUTH_LOG")
+                .map(PathBuf::from)
+                .or(file.auth_log_path)
+                .or(defaults.auth_log_path),
+            rate_limits: parse_var(&var, "SENTINEL_RATE_LIMITS")?
+                .or(file.rate_limits)
+                .unwrap_or(defaults.rate_limits),
+            min_severity: parse_var(&var, "SENTINEL_MIN_SEVERITY")?
+                .or(file.min_severity)
+                .unwrap_or(defaults.min_severity),
+            mock_event_rate: parse_var(&var, "SENTINEL_MOCK_EVENT_RATE")?
+                .or(file.mock_event_rate)
+                .unwrap_or(defaults.mock_event_rate),
+        };
+        config.validate()?;
+
+        // Only touch the state file once the rest of the config is known good
+        if configured_agent_id.is_none() && persist_agent_id {
+            config.agent_id = load_or_create_agent_id(&config.state_dir);
+        }
+
+        Ok(config)
+    }
+
+    /// Check that settings are internally consistent and usable.
+    pub fn validate(&self) -> Result<()> {
+        let uri: Uri = self.ingestor_url.parse()
+            .with_context(|| format!("Invalid ingestor URL: {}", self.ingestor_url))?;
+        if !matches!(uri.scheme_str(), Some("http") | Some("https")) || uri.authority().is_none() {
+            bail!("Ingestor URL must be an http:// or https:// address: {}", self.ingestor_url);
+        }
+
+        if self.tls_enabled {
+            if uri.scheme_str() != Some("https") {
+                bail!("tls_enabled requires an https:// ingestor URL: {}", self.ingestor_url);
+            }
+            if self.tls_ca_cert.is_none() {
+                bail!("tls_enabled requires tls_ca_cert");
+            }
+            if self.tls_client_cert.is_some() != self.tls_client_key.is_some() {
+                bail!("tls_client_cert and tls_client_key must be set together");
+            }
+        }
+
+        if self.batch_size == 0 {
+            bail!("batch_size must be non-zero");
+        }
+        if self.mock_event_rate == 0 {
+            bail!("mock_event_rate must be non-zero");
+        }
+        if self.max_buffer_size == 0 {
+            bail!("max_buffer_size must be non-zero");
+        }
+        if self.batch_size > self.max_buffer_size {
+            bail!(
+                "batch_size ({}) must not exceed max_buffer_size ({})",
+                self.batch_size,
+                self.max_buffer_size
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Read the persisted agent_id, generating and storing a new one if the
+/// state file is missing, empty, or corrupt. Failing to write the file is
+/// not fatal: the agent runs with the new id and warns.
+fn load_or_create_agent_id(state_dir: &Path) -> String {
+    let path = state_dir.join(AGENT_ID_FILE);
+
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => match Uuid::parse_str(contents.trim()) {
+            Ok(id) => return id.to_string(),
+            Err(_) => warn!("Agent ID state file {} is corrupt, regenerating", path.display()),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to read agent ID state file {}: {}", path.display(), e),
+    }
+
+    let agent_id = Uuid::new_v4().to_string();
+    let written = std::fs::create_dir_all(state_dir)
+        .and_then(|_| std::fs::write(&path, format!("{}\n", agent_id)));
+    match written {
+        Ok(()) => info!("Generated agent ID {} (saved to {})", agent_id, path.display()),
+        Err(e) => warn!(
+            "Failed to persist agent ID to {}: {}; a new ID will be generated on restart",
+            path.display(),
+            e
+        ),
+    }
+
+    agent_id
+}
+
+/// Parse an optional variable; `None` means it is absent, not invalid.
+fn parse_var<F, T>(var: &F, key: &str) -> Result<Option<T>>
+where
+    F: Fn(&str) -> Option<String>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    match var(key) {
+        None => Ok(None),
+        Some(raw) => raw.trim().parse::<T>()
+            .map(Some)
+            .map_err(|e| anyhow!("Invalid value for {}: {:?} ({})", key, raw, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::EventType;
+    use std::collections::HashMap;
+
+    /// Load from the given variables, keeping state in a throwaway
+    /// directory unless the caller sets SENTINEL_STATE_DIR itself.
+    fn load_from(vars: &[(&str, &str)]) -> Result<AgentConfig> {
+        let state_dir = tempfile::tempdir().unwrap();
+        let mut vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        vars.entry("SENTINEL_STATE_DIR".to_string())
+            .or_insert_with(|| state_dir.path().to_str().unwrap().to_string());
+        AgentConfig::load_with(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_defaults_when_unset() {
+        let config = load_from(&[]).unwrap();
+        assert_eq!(config.ingestor_url, "http://127.0.0.1:50051");
+        assert_eq!(config.tenant_id, "default");
+        assert!(config.dlp_enabled);
+        assert_eq!(config.batch_size, 100);
+        assert_eq!(config.max_buffer_size, 10000);
+        assert_eq!(config.metrics_port, 0);
+        assert_eq!(config.spool_max_bytes, 0);
+        assert_eq!(config.ioc_file, None);
+        assert_eq!(config.dlp_policy, None);
+        assert!(!config.tls_enabled);
+        assert_eq!(config.tls_ca_cert, None);
+        assert_eq!(config.auth_log_path, None);
+    }
+
+    #[test]
+    fn test_dlp_policy() {
+        let config = load_from(&[("SENTINEL_DLP_POLICY", "/etc/sentinel/dlp.json")]).unwrap();
+        assert_eq!(config.dlp_policy, Some(PathBuf::from("/etc/sentinel/dlp.json")));
+
+        let file = write_config(".toml", "dlp_policy = \"policy.json\"\n");
+        assert_eq!(AgentConfig::from_file(file.path()).unwrap().dlp_policy, Some(PathBuf::from("policy.json")));
+    }
+
+    #[test]
+    fn test_auth_log_path() {
+        let config = load_from(&[("SENTINEL_AUTH_LOG", "/var/log/secure")]).unwrap();
+        assert_eq!(config.auth_log_path, Some(PathBuf::from("/var/log/secure")));
+    }
+
+    #[test]
+    fn test_tls_settings() {
+        let tls = [
+            ("SENTINEL_INGESTOR_URL", "https://ingestor.example.com:443"),
+            ("SENTINEL_TLS_ENABLED", "true"),
+            ("SENTINEL_TLS_CA_CERT", "/etc/sentinel/ca.pem"),
+        ];
+        let config = load_from(&tls).unwrap();
+        assert!(config.tls_enabled);
+        assert_eq!(config.tls_ca_cert, Some(PathBuf::from("/etc/sentinel/ca.pem")));
+
+        let mtls = [&tls[..], &[
+            ("SENTINEL_TLS_CLIENT_CERT", "/etc/sentinel/agent.pem"),
+            ("SENTINEL_TLS_CLIENT_KEY", "/etc/sentinel/agent.key"),
+        ]].concat();
+        assert!(load_from(&mtls).is_ok());
+
+        // Plaintext URL, missing CA, or half a client identity
+        assert!(load_from(&[tls[1], tls[2]]).is_err());
+        assert!(load_from(&tls[..2]).is_err());
+        assert!(load_from(&[&tls[..], &[("SENTINEL_TLS_CLIENT_CERT", "/etc/sentinel/agent.pem")]].concat()).is_err());
+
+        let file = write_config(
+            ".toml",
+            "ingestor_url = \"https://ingestor:443\"\ntls_enabled = true\ntls_ca_cert = \"ca.pem\"\n",
+        );
+        assert_eq!(AgentConfig::from_file(file.path()).unwrap().tls_ca_cert, Some(PathBuf::from("ca.pem")));
+    }
+
+    #[test]
+    fn test_spool_max_bytes() {
+        let config = load_from(&[("SENTINEL_SPOOL_MAX_BYTES", "67108864")]).unwrap();
+        assert_eq!(config.spool_max_bytes, 64 * 1024 * 1024);
+        assert!(load_from(&[("SENTINEL_SPOOL_MAX_BYTES", "-1")]).is_err());
+
+        let file = write_config(".toml", "spool_max_bytes = 1048576\n");
+        assert_eq!(AgentConfig::from_file(file.path()).unwrap().spool_max_bytes, 1024 * 1024);
+    }
+
+    #[test]
+    fn test_metrics_port() {
+        let config = load_from(&[("SENTINEL_METRICS_PORT", "9464")]).unwrap();
+        assert_eq!(config.metrics_port, 9464);
+        assert!(load_from(&[("SENTINEL_METRICS_PORT", "70000")]).is_err());
+    }
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!(load_from(&[]).unwrap().log_format, LogFormat::Text);
+        assert_eq!(load_from(&[("SENTINEL_LOG_FORMAT", "JSON")]).unwrap().log_format, LogFormat::Json);
+        assert!(load_from(&[("SENTINEL_LOG_FORMAT", "xml")]).is_err());
+
+        let file = write_config(".toml", "log_format = \"json\"\n");
+        assert_eq!(AgentConfig::from_file(file.path()).unwrap().log_format, LogFormat::Json);
+    }
+
+    #[test]
+    fn test_rate_limits() {
+        assert!(load_from(&[]).unwrap().rate_limits.is_empty());
+
+        let config = load_from(&[("SENTINEL_RATE_LIMITS", "file_access=100,network_conn=50")]).unwrap();
+        assert_eq!(config.rate_limits.get(EventType::FileAccess), Some(100));
+        assert_eq!(config.rate_limits.get(EventType::NetworkConn), Some(50));
+        assert!(load_from(&[("SENTINEL_RATE_LIMITS", "file_access=0")]).is_err());
+
+        let file = write_config(".toml", "[rate_limits]\nfile_access = 100\nfile_modify = 20\n");
+        let config = AgentConfig::from_file(file.path()).unwrap();
+        assert_eq!(config.rate_limits.get(EventType::FileAccess), Some(100));
+        assert_eq!(config.rate_limits.get(EventType::FileModify), Some(20));
+
+        let file = write_config(".toml", "[rate_limits]\ndlp_violation = 10\n");
+        assert!(AgentConfig::from_file(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_min_severity() {
+        assert_eq!(load_from(&[]).unwrap().min_severity, Severity::Low);
+        assert_eq!(load_from(&[("SENTINEL_MIN_SEVERITY", "High")]).unwrap().min_severity, Severity::High);
+        assert!(load_from(&[("SENTINEL_MIN_SEVERITY", "urgent")]).is_err());
+
+        let file = write_config(".toml", "min_severity = \"medium\"\n");
+        assert_eq!(AgentConfig::from_file(file.path()).unwrap().min_severity, Severity::Medium);
+    }
+
+    #[test]
+    fn test_enforcement_mode() {
+        assert_eq!(load_from(&[]).unwrap().enforcement_mode, EnforcementMode::Monitor);
+        assert_eq!(
+            load_from(&[("SENTINEL_ENFORCEMENT_MODE", "Enforce")]).unwrap().enforcement_mode,
+            EnforcementMode::Enforce
+        );
+        assert_eq!(
+            load_from(&[("SENTINEL_ENFORCEMENT_MODE", "monitor")]).unwrap().enforcement_mode,
+            EnforcementMode::Monitor
+        );
+        assert!(load_from(&[("SENTINEL_ENFORCEMENT_MODE", "block")]).is_err());
+
+        let file = write_config(".toml", "enforcement_mode = \"enforce\"\n");
+        assert_eq!(
+            AgentConfig::from_file(file.path()).unwrap().enforcement_mode,
+            EnforcementMode::Enforce
+        );
+    }
+
+    #[test]
+    fn test_agent_id_persists_across_loads() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let dir = state_dir.path().to_str().unwrap();
+
+        let first = load_from(&[("SENTINEL_STATE_DIR", dir)]).unwrap();
+        let second = load_from(&[("SENTINEL_STATE_DIR", dir)]).unwrap();
+
+        assert_eq!(first.agent_id, second.agent_id);
+        let stored = std::fs::read_to_string(state_dir.path().join(AGENT_ID_FILE)).unwrap();
+        assert_eq!(stored.trim(), first.agent_id);
+    }
+
+    #[test]
+    fn test_corrupt_agent_id_state_is_rewritten() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let dir = state_dir.path().to_str().unwrap();
+        let path = state_dir.path().join(AGENT_ID_FILE);
+
+        for corrupt in ["", "not-a-uuid\n"] {
+            std::fs::write(&path, corrupt).unwrap();
+            let config = load_from(&[("SENTINEL_STATE_DIR", dir)]).unwrap();
+
+            assert!(Uuid::parse_str(&config.agent_id).is_ok());
+            assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), config.agent_id);
+        }
+    }
+
+    #[test]
+    fn test_configured_agent_id_skips_state_file() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let dir = state_dir.path().to_str().unwrap();
+
+        let config = load_from(&[("SENTINEL_STATE_DIR", dir), ("SENTINEL_AGENT_ID", "fixed-id")]).unwrap();
+
+        assert_eq!(config.agent_id, "fixed-id");
+        assert!(!state_dir.path().join(AGENT_ID_FILE).exists());
+    }
+
+    #[test]
+    fn test_unparseable_values_are_errors() {
+        assert!(load_from(&[("SENTINEL_BATCH_SIZE", "abc")]).is_err());
+        assert!(load_from(&[("SENTINEL_MAX_BUFFER", "-5")]).is_err());
+        assert!(load_from(&[("SENTINEL_DLP_ENABLED", "yes please")]).is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_bogus_env_var() {
+        let err = load_from(&[("SENTINEL_BATCH_SIZE", "abc")]).unwrap_err().to_string();
+        assert!(err.contains("SENTINEL_BATCH_SIZE"), "error should name the variable: {}", err);
+    }
+
+    #[test]
+    fn test_invalid_ingestor_url() {
+        assert!(load_from(&[("SENTINEL_INGESTOR_URL", "not a url")]).is_err());
+        assert!(load_from(&[("SENTINEL_INGESTOR_URL", "ftp://ingestor:21")]).is_err());
+        assert!(load_from(&[("SENTINEL_INGESTOR_URL", "https://ingestor.example.com:443")]).is_ok());
+    }
+
+    #[test]
+    fn test_buffer_size_constraints() {
+        assert!(load_from(&[("SENTINEL_BATCH_SIZE", "0")]).is_err());
+        assert!(load_from(&[("SENTINEL_MAX_BUFFER", "0")]).is_err());
+        assert!(load_from(&[("SENTINEL_BATCH_SIZE", "500"), ("SENTINEL_MAX_BUFFER", "100")]).is_err());
+        assert!(load_from(&[("SENTINEL_BATCH_SIZE", "100"), ("SENTINEL_MAX_BUFFER", "100")]).is_ok());
+    }
+
+    fn write_config(suffix: &str, contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        std::io::Write::write_all(&mut file, contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_from_file_toml() {
+        let file = write_config(
+            ".toml",
+            "tenant_id = \"acme\"\nbatch_size = 50\nebpf_object_path = \"target/bpf/process_monitor.o\"\n",
+        );
+        let config = AgentConfig::from_file(file.path()).unwrap();
+
+        assert_eq!(config.tenant_id, "acme");
+        assert_eq!(config.batch_size, 50);
+        assert_eq!(config.ebpf_object_path, PathBuf::from("target/bpf/process_monitor.o"));
+        // Omitted fields fall back to defaults
+        assert_eq!(config.max_buffer_size, 10000);
+    }
+
+    #[test]
+    fn test_from_file_json() {
+        let file = write_config(".json", r#"{"tenant_id": "acme", "dlp_enabled": false}"#);
+        let config = AgentConfig::from_file(file.path()).unwrap();
+
+        assert_eq!(config.tenant_id, "acme");
+        assert!(!config.dlp_enabled);
+    }
+
+    #[test]
+    fn test_env_overrides_config_file() {
+        let file = write_config(".toml", "tenant_id = \"from-file\"\nbatch_size = 50\n");
+        let path = file.path().to_str().unwrap();
+
+        let config = load_from(&[
+            ("SENTINEL_CONFIG_FILE", path),
+            ("SENTINEL_TENANT_ID", "from-env"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.tenant_id, "from-env");
+        assert_eq!(config.batch_size, 50, "file value applies when env var is absent");
+    }
+
+    #[test]
+    fn test_config_file_errors() {
+        let unknown = write_config(".toml", "tennant_id = \"typo\"\n");
+        assert!(AgentConfig::from_file(unknown.path()).is_err());
+
+        let invalid = write_config(".toml", "batch_size = 0\n");
+        assert!(AgentConfig::from_file(invalid.path()).is_err());
+
+        assert!(load_from(&[("SENTINEL_CONFIG_FILE", "/nonexistent/sentinel.toml")]).is_err());
     }
 }
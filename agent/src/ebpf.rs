@@ -4,20 +4,153 @@
 
 #![cfg(target_os = "linux")]
 
-use anyhow::{Result, Context};
+use anyhow::{anyhow, bail, Context, Result};
+use aya::maps::{MapData, RingBuf};
+use aya::programs::TracePoint;
+use aya::Bpf;
+use std::net::IpAddr;
 use std::sync::Arc;
+use tokio::io::unix::{AsyncFd, AsyncFdReadyMutGuard};
 use tokio::sync::mpsc;
-use tracing::{info, warn, error, debug};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, debug, warn};
 
 use crate::config::AgentConfig;
-use crate::dlp::DlpEngine;
-use crate::telemetry::{Event, EventType};
+use crate::config::EnforcementMode;
+use crate::dlp::{DlpEngine, EnforcementHook, Severity};
+use crate::health::{Collector, ComponentState, HEALTH};
+use crate::ioc::{IocStore, IOC_TACTIC, IOC_TECHNIQUE};
+use crate::mitre::TechniqueMatcher;
+use crate::ratelimit::RateLimiter;
+use crate::telemetry::{Event, EventContext, EventEmitter, EventType};
+
+// Capability bits from linux/capability.h
+const CAP_SYS_ADMIN: u32 = 21;
+const CAP_PERFMON: u32 = 38;
+const CAP_BPF: u32 = 39;
+
+/// Ring buffer map the process monitor writes `ProcessEvent`s into.
+const PROCESS_EVENTS_MAP: &str = "events";
+
+/// Ring buffer map the exit program writes `ProcessExitEvent`s into.
+const EXIT_EVENTS_MAP: &str = "exit_events";
+
+/// Length of the kernel's task command name (TASK_COMM_LEN).
+const TASK_COMM_LEN: usize = 16;
+
+/// Process exec record shared with the BPF program (`struct process_event`).
+/// Fields are in host byte order; the layout has no padding
+/// (4 + 4 + 8 + 16 = 32 bytes, 8-byte aligned).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessEvent {
+    pub pid: u32,
+    pub ppid: u32,
+    /// bpf_ktime_get_ns(): nanoseconds since boot, not wall-clock time
+    pub timestamp_ns: u64,
+    pub comm: [u8; TASK_COMM_LEN],
+}
+
+impl ProcessEvent {
+    /// Parse a record as emitted by the BPF side. Returns `None` if the
+    /// record is shorter than the struct; trailing bytes are ignored.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < std::mem::size_of::<Self>() {
+            return None;
+        }
+
+        let u32_at = |offset: usize| u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+        let mut comm = [0u8; TASK_COMM_LEN];
+        comm.copy_from_slice(&data[16..16 + TASK_COMM_LEN]);
+
+        Some(Self {
+            pid: u32_at(0),
+            ppid: u32_at(4),
+            timestamp_ns: u64::from_ne_bytes(data[8..16].try_into().unwrap()),
+            comm,
+        })
+    }
+
+    /// Command name up to the first NUL.
+    pub fn comm(&self) -> String {
+        comm_str(&self.comm)
+    }
+}
+
+/// Process exit record shared with the BPF program (`struct exit_event`).
+/// Host byte order, no padding (4 + 4 + 8 + 8 + 16 = 40 bytes, 8-byte aligned).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessExitEvent {
+    pub pid: u32,
+    /// `task->exit_code` in wait(2) status form (exit status << 8 | signal)
+    pub exit_code: i32,
+    /// bpf_ktime_get_ns() at exit
+    pub timestamp_ns: u64,
+    /// `task->start_time`: nanoseconds since boot when the task was forked
+    pub start_time_ns: u64,
+    pub comm: [u8; TASK_COMM_LEN],
+}
+
+impl ProcessExitEvent {
+    /// Parse a record as emitted by the BPF side. Returns `None` if the
+    /// record is shorter than the struct; trailing bytes are ignored.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < std::mem::size_of::<Self>() {
+            return None;
+        }
+
+        let u64_at = |offset: usize| u64::from_ne_bytes(data[offset..offset + 8].try_into().unwrap());
+        let mut comm = [0u8; TASK_COMM_LEN];
+        comm.copy_from_slice(&data[24..24 + TASK_COMM_LEN]);
+
+        Some(Self {
+            pid: u32::from_ne_bytes(data[0..4].try_into().unwrap()),
+            exit_code: i32::from_ne_bytes(data[4..8].try_into().unwrap()),
+            timestamp_ns: u64_at(8),
+            start_time_ns: u64_at(16),
+            comm,
+        })
+    }
+
+    /// Command name up to the first NUL.
+    pub fn comm(&self) -> String {
+        comm_str(&self.comm)
+    }
+
+    /// Exit status passed to exit(2), meaningful when `signal()` is 0.
+    pub fn exit_status(&self) -> i32 {
+        (self.exit_code >> 8) & 0xff
+    }
+
+    /// Signal that terminated the process, or 0 for a normal exit.
+    pub fn signal(&self) -> i32 {
+        self.exit_code & 0x7f
+    }
+}
+
+fn comm_str(comm: &[u8; TASK_COMM_LEN]) -> String {
+    let len = comm.iter().position(|&b| b == 0).unwrap_or(TASK_COMM_LEN);
+    String::from_utf8_lossy(&comm[..len]).into_owned()
+}
 
 /// eBPF-based event collector for Linux systems
 pub struct EbpfCollector {
-    event_tx: mpsc::Sender<Event>,
+    events: EventEmitter,
     config: AgentConfig,
+    /// Identity stamped on every event, resolved once at construction.
+    event_context: EventContext,
     dlp_engine: Arc<DlpEngine>,
+    /// Cancelled when the agent shuts down; stops the event loop.
+    shutdown: CancellationToken,
+    /// Loaded process monitor object; dropping it detaches its programs.
+    process_bpf: Option<Bpf>,
+    /// Maps exec events to MITRE ATT&CK techniques.
+    techniques: TechniqueMatcher,
+    /// Blocking action for DLP matches; only used in Enforce mode.
+    enforcement_hook: Option<Arc<dyn EnforcementHook>>,
+    /// Known-bad destinations checked for every network connection.
+    iocs: Arc<IocStore>,
 }
 
 impl EbpfCollector {
@@ -25,20 +158,50 @@ impl EbpfCollector {
         event_tx: mpsc::Sender<Event>,
         config: AgentConfig,
         dlp_engine: Arc<DlpEngine>,
+        shutdown: CancellationToken,
     ) -> Self {
         Self {
-            event_tx,
+            events: EventEmitter::new(event_tx, "eBPF"),
+            event_context: EventContext::from_config(&config),
             config,
             dlp_engine,
+            shutdown,
+            process_bpf: None,
+            techniques: TechniqueMatcher::new(),
+            enforcement_hook: None,
+            iocs: Arc::new(IocStore::new()),
         }
     }
 
+    /// Use `iocs` for threat-intel matching of network destinations.
+    pub fn with_ioc_store(mut self, iocs: Arc<IocStore>) -> Self {
+        self.iocs = iocs;
+        self
+    }
+
+    /// Sample emitted events through the agent-wide `limiter`.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.events = self.events.with_rate_limiter(limiter);
+        self
+    }
+
+    /// Install the action taken on DLP matches in `EnforcementMode::Enforce`.
+    pub fn with_enforcement_hook(mut self, hook: Arc<dyn EnforcementHook>) -> Self {
+        self.enforcement_hook = Some(hook);
+        self
+    }
+
+    /// Number of events dropped because the telemetry channel was full.
+    pub fn dropped_events(&self) -> u64 {
+        self.events.dropped()
+    }
+
     /// Initialize and load eBPF programs for various event types
     pub async fn start(&mut self) -> Result<()> {
         info!("Initializing Linux eBPF collectors...");
 
-        // TODO: Load eBPF programs using aya
-        // This is a scaffold for future implementation
+        // Fail up front with a readable error instead of an EPERM from the loader
+        check_bpf_capabilities()?;
 
         // Load process monitoring eBPF program
         self.load_process_monitor().await?;
@@ -50,6 +213,7 @@ impl EbpfCollector {
         self.load_network_monitor().await?;
 
         info!("All eBPF programs loaded successfully");
+        HEALTH.set_collector(Collector::Ebpf, ComponentState::Running);
 
         // Start event processing loop
         self.process_events().await?;
@@ -58,22 +222,44 @@ impl EbpfCollector {
     }
 
     /// Load eBPF program for process monitoring (exec, fork, exit)
-    async fn load_process_monitor(&self) -> Result<()> {
-        info!("Loading process monitoring eBPF program...");
-
-        // TODO: Implement eBPF program loading
-        // Example structure:
-        //
-        // #[cfg(target_os = "linux")]
-        // let mut bpf = aya::Bpf::load(include_bytes_aligned!(
-        //     "../../target/bpf/process_monitor.o"
-        // ))?;
-        //
-        // let program: &mut TracePoint = bpf.program_mut("process_exec").unwrap().try_into()?;
-        // program.load()?;
-        // program.attach("sched", "sched_process_exec")?;
-
-        info!("Process monitoring eBPF program loaded (skeleton)");
+    async fn load_process_monitor(&mut self) -> Result<()> {
+        let path = &self.config.ebpf_object_path;
+        info!("Loading process monitoring eBPF program from {}...", path.display());
+
+        let mut bpf = Bpf::load_file(path).with_context(|| {
+            format!("Failed to load eBPF object {} (missing file or unsupported kernel)", path.display())
+        })?;
+
+        let program: &mut TracePoint = bpf
+            .program_mut("process_exec")
+            .ok_or_else(|| anyhow!("eBPF object {} has no process_exec program", path.display()))?
+            .try_into()
+            .context("process_exec is not a tracepoint program")?;
+        program.load().context(
+            "Kernel rejected the process_exec program (kernel too old or verifier failure)",
+        )?;
+        program.attach("sched", "sched_process_exec")
+            .context("Failed to attach process_exec to tracepoint sched/sched_process_exec")?;
+        info!("Process monitoring eBPF program attached to sched/sched_process_exec");
+
+        // Older objects predate the exit program; starts are still reported
+        match bpf.program_mut("process_exit") {
+            Some(program) => {
+                let program: &mut TracePoint = program
+                    .try_into()
+                    .context("process_exit is not a tracepoint program")?;
+                program.load().context("Kernel rejected the process_exit program")?;
+                program.attach("sched", "sched_process_exit")
+                    .context("Failed to attach process_exit to tracepoint sched/sched_process_exit")?;
+                info!("Process exit eBPF program attached to sched/sched_process_exit");
+            }
+            None => warn!(
+                "eBPF object {} has no process_exit program; process termination events disabled",
+                path.display()
+            ),
+        }
+
+        self.process_bpf = Some(bpf);
         Ok(())
     }
 
@@ -105,33 +291,108 @@ impl EbpfCollector {
         Ok(())
     }
 
-    /// Process events from eBPF ring buffers
-    async fn process_events(&self) -> Result<()> {
+    /// Process events from eBPF ring buffers until shutdown is requested
+    async fn process_events(&mut self) -> Result<()> {
         info!("Starting eBPF event processing loop...");
 
-        // TODO: Implement event processing from eBPF perf/ring buffers
-        // This would:
-        // 1. Read events from kernel space via aya ring buffers
-        // 2. Parse event data structures
-        // 3. Apply MITRE ATT&CK mappings
-        // 4. Run DLP scans on file operations
-        // 5. Send to telemetry channel
+        let Some(bpf) = self.process_bpf.as_mut() else {
+            // Nothing attached; idle until shutdown is requested
+            self.shutdown.cancelled().await;
+            info!("eBPF event processing loop stopped");
+            return Ok(());
+        };
+
+        let map = bpf.take_map(PROCESS_EVENTS_MAP)
+            .ok_or_else(|| anyhow!("eBPF object has no {} map", PROCESS_EVENTS_MAP))?;
+        let ring_buf = RingBuf::try_from(map)
+            .with_context(|| format!("{} map is not a ring buffer", PROCESS_EVENTS_MAP))?;
+        let mut ring_buf: AsyncFd<RingBuf<MapData>> = AsyncFd::new(ring_buf)
+            .context("Failed to register ring buffer with the tokio reactor")?;
+
+        let mut exit_buf = match bpf.take_map(EXIT_EVENTS_MAP) {
+            Some(map) => {
+                let exit_buf = RingBuf::try_from(map)
+                    .with_context(|| format!("{} map is not a ring buffer", EXIT_EVENTS_MAP))?;
+                Some(AsyncFd::new(exit_buf).context("Failed to register exit ring buffer with the tokio reactor")?)
+            }
+            None => None,
+        };
 
-        // Placeholder: Sleep to keep the function running
         loop {
-            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-            debug!("eBPF collector heartbeat (skeleton mode)");
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    info!("eBPF event processing loop stopped");
+                    return Ok(());
+                }
+                guard = ring_buf.readable_mut() => {
+                    let mut guard = guard.context("Ring buffer poll failed")?;
+                    let ring_buf = guard.get_inner_mut();
+                    while let Some(record) = ring_buf.next() {
+                        match ProcessEvent::parse(&record) {
+                            Some(event) => self.handle_process_exec(&event)?,
+                            None => warn!("Dropping short process event record ({} bytes)", record.len()),
+                        }
+                    }
+                    guard.clear_ready();
+                }
+                guard = readable(&mut exit_buf) => {
+                    let mut guard = guard.context("Exit ring buffer poll failed")?;
+                    let exit_buf = guard.get_inner_mut();
+                    while let Some(record) = exit_buf.next() {
+                        match ProcessExitEvent::parse(&record) {
+                            Some(event) => self.handle_process_exit(&event)?,
+                            None => warn!("Dropping short process exit record ({} bytes)", record.len()),
+                        }
+                    }
+                    guard.clear_ready();
+                }
+            }
         }
     }
 
-    /// Handle process execution event from eBPF
-    #[allow(dead_code)]
-    fn handle_process_exec(&self, _pid: u32, _ppid: u32, _cmdline: &str, _user: &str) -> Result<()> {
-        // TODO: Create Event and send to channel
-        // Determine MITRE tactic based on process characteristics
-        // Example: suspicious PowerShell execution = T1059.001
+    /// Handle process exit event from eBPF
+    fn handle_process_exit(&self, exit: &ProcessExitEvent) -> Result<()> {
+        // pid + start time identify the process across pid reuse
+        let payload = serde_json::json!({
+            "pid": exit.pid,
+            "comm": exit.comm(),
+            "exit_code": exit.exit_status(),
+            "signal": exit.signal(),
+            "start_ktime_ns": exit.start_time_ns,
+            "ktime_ns": exit.timestamp_ns,
+        });
+
+        // An exit is not itself an ATT&CK tactic
+        let event = Event::new(&self.event_context, EventType::ProcessTerminate, String::new(), payload.to_string());
+
+        self.events.emit_event(event);
+        Ok(())
+    }
 
-        debug!("Process exec event (skeleton)");
+    /// Handle process execution event from eBPF
+    fn handle_process_exec(&self, exec: &ProcessEvent) -> Result<()> {
+        let comm = exec.comm();
+        let payload = serde_json::json!({
+            "pid": exec.pid,
+            "ppid": exec.ppid,
+            "comm": comm,
+            "ktime_ns": exec.timestamp_ns,
+        });
+
+        // Only the command name is captured in-kernel, so it doubles as the command line
+        let (tactic, technique) = self.techniques
+            .infer(&comm, &comm)
+            .unwrap_or(("TA0002_Execution", ""));
+
+        let mut event = Event::new(
+            &self.event_context,
+            EventType::ProcessStart,
+            tactic.to_string(),
+            payload.to_string(),
+        );
+        event.mitre_technique = technique.to_string();
+
+        self.events.emit_event(event);
         Ok(())
     }
 
@@ -139,20 +400,37 @@ impl EbpfCollector {
     #[allow(dead_code)]
     fn handle_file_operation(
         &self,
-        _operation: &str,
-        _path: &str,
-        _pid: u32,
-        _buffer: &[u8],
+        operation: &str,
+        path: &str,
+        pid: u32,
+        buffer: &[u8],
     ) -> Result<()> {
-        // TODO: Run DLP scan on file buffer
-        // if self.dlp_engine.should_scan(buffer) {
-        //     let matches = self.dlp_engine.scan_buffer(buffer);
-        //     if !matches.is_empty() {
-        //         // Create DLP violation event
-        //     }
-        // }
-
-        debug!("File operation event (skeleton)");
+        // Runs for every vfs_write: never block here
+        if !self.config.dlp_enabled || !self.dlp_engine.should_scan(buffer) {
+            return Ok(());
+        }
+
+        let mode = self.config.enforcement_mode;
+        for dlp_match in self.dlp_engine.scan_buffer(buffer) {
+            let mut payload = dlp_match.payload(mode);
+            payload["path"] = path.into();
+            payload["operation"] = operation.into();
+            payload["pid"] = pid.into();
+
+            let mut event = dlp_match.into_event(&self.event_context, mode);
+            event.payload = payload.to_string();
+            self.events.emit_event(event);
+
+            // Monitor mode reports only, whatever the severity
+            if mode == EnforcementMode::Enforce {
+                if let Some(hook) = &self.enforcement_hook {
+                    if let Err(e) = hook.enforce(&dlp_match, pid, path) {
+                        warn!("Enforcement for rule {} on {} failed: {}", dlp_match.rule_id, path, e);
+                    }
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -160,40 +438,502 @@ impl EbpfCollector {
     #[allow(dead_code)]
     fn handle_network_connection(
         &self,
-        _src_ip: &str,
-        _dst_ip: &str,
-        _dst_port: u16,
-        _protocol: &str,
+        src_ip: &str,
+        dst_ip: &str,
+        dst_port: u16,
+        protocol: &str,
     ) -> Result<()> {
-        // TODO: Check against threat intelligence feeds
-        // Create network connection event
+        let dst_addr: IpAddr = dst_ip
+            .parse()
+            .with_context(|| format!("Invalid destination address: {}", dst_ip))?;
+
+        let mut payload = serde_json::json!({
+            "src_ip": src_ip,
+            "dst_ip": dst_ip,
+            "dst_port": dst_port,
+            "protocol": protocol,
+        });
+        // Only a threat-intel hit makes a connection look like C2
+        let mut event = Event::new(&self.event_context, EventType::NetworkConn, String::new(), String::new());
+
+        if let Some(ioc_id) = self.iocs.lookup_ip(dst_addr) {
+            debug!("Connection to {} matched IOC {}", dst_ip, ioc_id);
+            payload["ioc_id"] = ioc_id.into();
+            event.mitre_tactic = IOC_TACTIC.to_string();
+            event.mitre_technique = IOC_TECHNIQUE.to_string();
+            event.severity = Severity::High.into();
+        }
 
-        debug!("Network connection event (skeleton)");
+        event.payload = payload.to_string();
+        self.events.emit_event(event);
         Ok(())
     }
 }
 
+/// Wait until `buf` has records. A missing ring buffer never becomes readable.
+async fn readable(
+    buf: &mut Option<AsyncFd<RingBuf<MapData>>>,
+) -> std::io::Result<AsyncFdReadyMutGuard<'_, RingBuf<MapData>>> {
+    match buf {
+        Some(buf) => buf.readable_mut().await,
+        None => std::future::pending().await,
+    }
+}
+
 /// Entry point called from main.rs to start eBPF monitoring
+/// Returns once `shutdown` is cancelled.
 pub async fn start_collectors(
     event_tx: mpsc::Sender<Event>,
     config: AgentConfig,
     dlp_engine: Arc<DlpEngine>,
+    iocs: Arc<IocStore>,
+    rate_limiter: Arc<RateLimiter>,
+    shutdown: CancellationToken,
 ) -> Result<()> {
-    let mut collector = EbpfCollector::new(event_tx, config, dlp_engine);
-    collector.start().await?;
-    Ok(())
+    HEALTH.set_collector(Collector::Ebpf, ComponentState::Starting);
+    let mut collector = EbpfCollector::new(event_tx, config, dlp_engine, shutdown)
+        .with_ioc_store(iocs)
+        .with_rate_limiter(rate_limiter);
+    let result = collector.start().await;
+    HEALTH.collector_finished(Collector::Ebpf, &result);
+    result
+}
+
+/// Verify the process may load and attach tracepoint programs: either
+/// CAP_SYS_ADMIN, or CAP_BPF together with CAP_PERFMON (Linux 5.8+).
+pub fn check_bpf_capabilities() -> Result<()> {
+    let status = std::fs::read_to_string("/proc/self/status")
+        .context("Failed to read /proc/self/status for capability check")?;
+    let cap_eff = parse_cap_eff(&status)
+        .ok_or_else(|| anyhow!("No CapEff entry in /proc/self/status"))?;
+    require_bpf_capabilities(cap_eff)
+}
+
+/// Extract the effective capability mask from /proc/<pid>/status contents.
+fn parse_cap_eff(status: &str) -> Option<u64> {
+    status.lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+}
+
+fn require_bpf_capabilities(cap_eff: u64) -> Result<()> {
+    let has = |cap: u32| cap_eff & (1 << cap) != 0;
+
+    if has(CAP_SYS_ADMIN) || (has(CAP_BPF) && has(CAP_PERFMON)) {
+        return Ok(());
+    }
+
+    bail!(
+        "Insufficient privileges to load eBPF programs: need CAP_BPF and CAP_PERFMON \
+         (or CAP_SYS_ADMIN); effective capabilities are {:#x}",
+        cap_eff
+    )
 }
 
 // eBPF program source code (would be in separate .bpf.c files)
 // This is just documentation of what the eBPF programs would do:
 //
+// struct process_event {
+//     __u32 pid;
+//     __u32 ppid;
+//     __u64 timestamp;
+//     char comm[TASK_COMM_LEN];
+// };
+//
+// struct {
+//     __uint(type, BPF_MAP_TYPE_RINGBUF);
+//     __uint(max_entries, 256 * 1024);
+// } events SEC(".maps");
+//
 // SEC("tracepoint/sched/sched_process_exec")
 // int process_exec(struct trace_event_raw_sched_process_exec *ctx) {
+//     struct task_struct *task = (struct task_struct *)bpf_get_current_task();
 //     struct process_event event = {};
 //     event.pid = bpf_get_current_pid_tgid() >> 32;
+//     event.ppid = BPF_CORE_READ(task, real_parent, tgid);
 //     event.timestamp = bpf_ktime_get_ns();
 //     bpf_get_current_comm(&event.comm, sizeof(event.comm));
 //
-//     bpf_perf_event_output(ctx, &events, BPF_F_CURRENT_CPU, &event, sizeof(event));
+//     bpf_ringbuf_output(&events, &event, sizeof(event), 0);
 //     return 0;
 // }
+//
+// struct exit_event {
+//     __u32 pid;
+//     __s32 exit_code;
+//     __u64 timestamp;
+//     __u64 start_time;
+//     char comm[TASK_COMM_LEN];
+// };
+//
+// struct {
+//     __uint(type, BPF_MAP_TYPE_RINGBUF);
+//     __uint(max_entries, 256 * 1024);
+// } exit_events SEC(".maps");
+//
+// SEC("tracepoint/sched/sched_process_exit")
+// int process_exit(struct trace_event_raw_sched_process_template *ctx) {
+//     __u64 pid_tgid = bpf_get_current_pid_tgid();
+//     // Threads exit too; only report the thread group leader
+//     if ((__u32)pid_tgid != pid_tgid >> 32)
+//         return 0;
+//
+//     struct task_struct *task = (struct task_struct *)bpf_get_current_task();
+//     struct exit_event event = {};
+//     event.pid = pid_tgid >> 32;
+//     event.exit_code = BPF_CORE_READ(task, exit_code);
+//     event.timestamp = bpf_ktime_get_ns();
+//     event.start_time = BPF_CORE_READ(task, start_time);
+//     bpf_get_current_comm(&event.comm, sizeof(event.comm));
+//
+//     bpf_ringbuf_output(&exit_events, &event, sizeof(event), 0);
+//     return 0;
+// }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn test_config() -> AgentConfig {
+        AgentConfig {
+            agent_id: "agent-1".to_string(),
+            tenant_id: "tenant-1".to_string(),
+            ..AgentConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_loop_stops_on_shutdown() {
+        let (event_tx, _event_rx) = mpsc::channel(16);
+        let shutdown = CancellationToken::new();
+        let mut collector = EbpfCollector::new(
+            event_tx,
+            test_config(),
+            Arc::new(DlpEngine::new()),
+            shutdown.clone(),
+        );
+        let handle = tokio::spawn(async move { collector.process_events().await });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!handle.is_finished(), "event loop should run until shutdown");
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("event loop should return promptly after shutdown")
+            .unwrap()
+            .unwrap();
+    }
+
+    fn process_event_bytes(pid: u32, ppid: u32, timestamp_ns: u64, comm: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&pid.to_ne_bytes());
+        data.extend_from_slice(&ppid.to_ne_bytes());
+        data.extend_from_slice(&timestamp_ns.to_ne_bytes());
+        let mut comm_field = [0u8; TASK_COMM_LEN];
+        comm_field[..comm.len()].copy_from_slice(comm);
+        data.extend_from_slice(&comm_field);
+        data
+    }
+
+    #[test]
+    fn test_process_event_layout() {
+        assert_eq!(std::mem::size_of::<ProcessEvent>(), 32);
+        assert_eq!(std::mem::align_of::<ProcessEvent>(), 8);
+    }
+
+    #[test]
+    fn test_parse_process_event() {
+        let data = process_event_bytes(4242, 1, 123_456_789, b"bash");
+        let event = ProcessEvent::parse(&data).unwrap();
+
+        assert_eq!(event.pid, 4242);
+        assert_eq!(event.ppid, 1);
+        assert_eq!(event.timestamp_ns, 123_456_789);
+        assert_eq!(event.comm(), "bash");
+
+        // Full-length comm has no terminator
+        let data = process_event_bytes(1, 0, 0, b"0123456789abcdef");
+        assert_eq!(ProcessEvent::parse(&data).unwrap().comm(), "0123456789abcdef");
+
+        assert!(ProcessEvent::parse(&data[..31]).is_none());
+    }
+
+    fn exit_event_bytes(pid: u32, exit_code: i32, timestamp_ns: u64, start_time_ns: u64, comm: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&pid.to_ne_bytes());
+        data.extend_from_slice(&exit_code.to_ne_bytes());
+        data.extend_from_slice(&timestamp_ns.to_ne_bytes());
+        data.extend_from_slice(&start_time_ns.to_ne_bytes());
+        let mut comm_field = [0u8; TASK_COMM_LEN];
+        comm_field[..comm.len()].copy_from_slice(comm);
+        data.extend_from_slice(&comm_field);
+        data
+    }
+
+    #[test]
+    fn test_parse_process_exit_event() {
+        assert_eq!(std::mem::size_of::<ProcessExitEvent>(), 40);
+
+        // exit(3): status in the high byte
+        let data = exit_event_bytes(4242, 3 << 8, 900, 100, b"make");
+        let event = ProcessExitEvent::parse(&data).unwrap();
+        assert_eq!(event.pid, 4242);
+        assert_eq!(event.exit_status(), 3);
+        assert_eq!(event.signal(), 0);
+        assert_eq!(event.timestamp_ns, 900);
+        assert_eq!(event.start_time_ns, 100);
+        assert_eq!(event.comm(), "make");
+
+        // Killed by SIGKILL
+        let event = ProcessExitEvent::parse(&exit_event_bytes(1, 9, 0, 0, b"sleep")).unwrap();
+        assert_eq!(event.signal(), 9);
+
+        assert!(ProcessExitEvent::parse(&data[..39]).is_none());
+    }
+
+    #[test]
+    fn test_handle_process_exit_emits_event() {
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let collector = EbpfCollector::new(
+            event_tx,
+            test_config(),
+            Arc::new(DlpEngine::new()),
+            CancellationToken::new(),
+        );
+
+        let exit = ProcessExitEvent::parse(&exit_event_bytes(4242, 1 << 8, 900, 100, b"curl")).unwrap();
+        collector.handle_process_exit(&exit).unwrap();
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::ProcessTerminate);
+        assert_eq!(event.tenant_id, "tenant-1");
+        assert_eq!(event.mitre_tactic, "");
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["pid"], 4242);
+        assert_eq!(payload["exit_code"], 1);
+        assert_eq!(payload["signal"], 0);
+        assert_eq!(payload["start_ktime_ns"], 100);
+        assert_eq!(payload["comm"], "curl");
+    }
+
+    #[test]
+    fn test_handle_process_exec_emits_event() {
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let collector = EbpfCollector::new(
+            event_tx,
+            test_config(),
+            Arc::new(DlpEngine::new()),
+            CancellationToken::new(),
+        );
+
+        let exec = ProcessEvent::parse(&process_event_bytes(4242, 1, 0, b"curl")).unwrap();
+        collector.handle_process_exec(&exec).unwrap();
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::ProcessStart);
+        assert_eq!(event.tenant_id, "tenant-1");
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["pid"], 4242);
+        assert_eq!(payload["comm"], "curl");
+        assert_eq!(event.mitre_tactic, "TA0002_Execution");
+        assert!(event.mitre_technique.is_empty());
+
+        let exec = ProcessEvent::parse(&process_event_bytes(4243, 1, 0, b"whoami")).unwrap();
+        collector.handle_process_exec(&exec).unwrap();
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.mitre_tactic, "TA0007_Discovery");
+        assert_eq!(event.mitre_technique, "T1033");
+    }
+
+    fn fingerprinted_buffer(dlp: &DlpEngine) -> Vec<u8> {
+        let buffer: Vec<u8> = (0..256).map(|i| b'a' + (i % 26) as u8).collect();
+        let hash = dlp.hash_chunk(&buffer[64..128]);
+        dlp.add_fingerprint(&hash, "SSN-RULE".to_string(), crate::dlp::Severity::High);
+        buffer
+    }
+
+    #[test]
+    fn test_file_operation_emits_dlp_violation() {
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let dlp = Arc::new(DlpEngine::new());
+        let buffer = fingerprinted_buffer(&dlp);
+        let collector = EbpfCollector::new(event_tx, test_config(), dlp, CancellationToken::new());
+
+        collector.handle_file_operation("write", "/home/user/export.csv", 777, &buffer).unwrap();
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::DlpViolation);
+        assert_eq!(event.tenant_id, "tenant-1");
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["rule_id"], "SSN-RULE");
+        assert_eq!(payload["path"], "/home/user/export.csv");
+        assert_eq!(payload["operation"], "write");
+        assert_eq!(payload["pid"], 777);
+    }
+
+    /// Records every enforcement request instead of blocking anything.
+    #[derive(Default)]
+    struct RecordingHook {
+        calls: std::sync::Mutex<Vec<(String, u32)>>,
+    }
+
+    impl EnforcementHook for RecordingHook {
+        fn enforce(&self, dlp_match: &crate::dlp::DlpMatch, pid: u32, _path: &str) -> Result<()> {
+            self.calls.lock().unwrap().push((dlp_match.rule_id.clone(), pid));
+            Ok(())
+        }
+    }
+
+    fn critical_buffer(dlp: &DlpEngine) -> Vec<u8> {
+        let buffer: Vec<u8> = (0..256).map(|i| b'A' + (i % 26) as u8).collect();
+        dlp.add_fingerprint(&dlp.hash_chunk(&buffer[0..64]), "CCN-VISA".to_string(), crate::dlp::Severity::Critical);
+        buffer
+    }
+
+    #[test]
+    fn test_monitor_mode_never_enforces() {
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let dlp = Arc::new(DlpEngine::new());
+        let buffer = critical_buffer(&dlp);
+        let hook = Arc::new(RecordingHook::default());
+        let collector = EbpfCollector::new(event_tx, test_config(), dlp, CancellationToken::new())
+            .with_enforcement_hook(hook.clone());
+
+        collector.handle_file_operation("write", "/tmp/cards.csv", 31, &buffer).unwrap();
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.severity, 4);
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["enforcement_mode"], "monitor");
+        assert!(hook.calls.lock().unwrap().is_empty(), "monitor mode must not enforce");
+    }
+
+    #[test]
+    fn test_enforce_mode_invokes_hook() {
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let dlp = Arc::new(DlpEngine::new());
+        let buffer = critical_buffer(&dlp);
+        let hook = Arc::new(RecordingHook::default());
+        let config = AgentConfig {
+            enforcement_mode: EnforcementMode::Enforce,
+            ..test_config()
+        };
+        let collector = EbpfCollector::new(event_tx, config, dlp, CancellationToken::new())
+            .with_enforcement_hook(hook.clone());
+
+        collector.handle_file_operation("write", "/tmp/cards.csv", 31, &buffer).unwrap();
+
+        let payload: serde_json::Value = serde_json::from_str(&event_rx.try_recv().unwrap().payload).unwrap();
+        assert_eq!(payload["enforcement_mode"], "enforce");
+        assert_eq!(*hook.calls.lock().unwrap(), vec![("CCN-VISA".to_string(), 31)]);
+    }
+
+    #[test]
+    fn test_file_operation_counts_drops_when_channel_full() {
+        let (event_tx, _event_rx) = mpsc::channel(1);
+        let dlp = Arc::new(DlpEngine::new());
+        let buffer = fingerprinted_buffer(&dlp);
+        let collector = EbpfCollector::new(event_tx, test_config(), dlp, CancellationToken::new());
+
+        for _ in 0..3 {
+            collector.handle_file_operation("write", "/tmp/out", 1, &buffer).unwrap();
+        }
+
+        assert_eq!(collector.dropped_events(), 2);
+    }
+
+    #[test]
+    fn test_capability_check_rejects_unprivileged() {
+        let err = require_bpf_capabilities(0).unwrap_err().to_string();
+        assert!(err.contains("CAP_BPF"), "unexpected error: {}", err);
+
+        // CAP_BPF alone cannot attach tracepoints
+        assert!(require_bpf_capabilities(1 << CAP_BPF).is_err());
+        assert!(require_bpf_capabilities((1 << CAP_BPF) | (1 << CAP_PERFMON)).is_ok());
+        assert!(require_bpf_capabilities(1 << CAP_SYS_ADMIN).is_ok());
+    }
+
+    #[test]
+    fn test_parse_cap_eff() {
+        let status = "Name:\tsentinel-agent\nCapInh:\t0000000000000000\nCapEff:\t000001ffffffffff\n";
+        assert_eq!(parse_cap_eff(status), Some(0x1ff_ffff_ffff));
+        assert_eq!(parse_cap_eff("Name:\tfoo\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_start_fails_with_missing_object() {
+        let (event_tx, _event_rx) = mpsc::channel(16);
+        let config = AgentConfig {
+            ebpf_object_path: "/nonexistent/process_monitor.o".into(),
+            ..test_config()
+        };
+
+        // Unprivileged runs fail the capability check, privileged ones the load
+        let iocs = Arc::new(IocStore::new());
+        let limiter = Arc::new(RateLimiter::new(&Default::default()));
+        let err = start_collectors(event_tx, config, Arc::new(DlpEngine::new()), iocs, limiter, CancellationToken::new())
+            .await
+            .unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("CAP_BPF") || message.contains("/nonexistent/process_monitor.o"),
+            "unexpected error: {}",
+            message
+        );
+    }
+
+    fn ioc_collector(event_tx: mpsc::Sender<Event>) -> EbpfCollector {
+        let iocs = IocStore::from_indicators(vec![
+            crate::ioc::Indicator { id: "IOC-C2".to_string(), value: "203.0.113.7".to_string() },
+            crate::ioc::Indicator { id: "IOC-NET".to_string(), value: "2001:db8:bad::/48".to_string() },
+        ])
+        .unwrap();
+        EbpfCollector::new(event_tx, test_config(), Arc::new(DlpEngine::new()), CancellationToken::new())
+            .with_ioc_store(Arc::new(iocs))
+    }
+
+    #[test]
+    fn test_network_connection_ioc_hit() {
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let collector = ioc_collector(event_tx);
+
+        collector.handle_network_connection("10.0.0.5", "203.0.113.7", 443, "tcp").unwrap();
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::NetworkConn);
+        assert_eq!(event.severity, 3);
+        assert_eq!(event.mitre_tactic, "TA0011_Command_and_Control");
+        assert_eq!(event.mitre_technique, "T1071");
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["ioc_id"], "IOC-C2");
+        assert_eq!(payload["dst_port"], 443);
+
+        collector.handle_network_connection("fd00::5", "2001:db8:bad:7::1", 8443, "tcp").unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&event_rx.try_recv().unwrap().payload).unwrap();
+        assert_eq!(payload["ioc_id"], "IOC-NET");
+
+        // A destination outside the feed carries no tactic or technique
+        collector.handle_network_connection("10.0.0.5", "93.184.216.34", 443, "tcp").unwrap();
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.mitre_tactic, "");
+        assert_eq!(event.mitre_technique, "");
+    }
+
+    #[test]
+    fn test_network_connection_miss_is_not_flagged() {
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let collector = ioc_collector(event_tx);
+
+        collector.handle_network_connection("10.0.0.5", "93.184.216.34", 443, "tcp").unwrap();
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.severity, 1);
+        assert!(event.mitre_tactic.is_empty());
+        assert!(event.mitre_technique.is_empty());
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert!(payload.get("ioc_id").is_none());
+
+        assert!(collector.handle_network_connection("10.0.0.5", "not-an-ip", 443, "tcp").is_err());
+    }
+}
@@ -1,13 +1,16 @@
 // Windows ETW (Event Tracing for Windows) Consumer
-// Subscribes to kernel providers for process, file, and network events.
+// Subscribes to kernel providers for process, file, network, and registry events.
 // Performance-critical: Must process events with minimal latency.
 
 #![cfg(target_os = "windows")]
 
-use anyhow::{Result, Context};
+use anyhow::Result;
+use std::ffi::c_void;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tracing::{info, warn, error, debug};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn, debug};
 
 use windows::{
     core::*,
@@ -16,20 +19,157 @@ use windows::{
 };
 
 use crate::config::AgentConfig;
-use crate::dlp::DlpEngine;
-use crate::telemetry::{Event, EventType};
+use crate::dlp::{DlpEngine, Severity};
+use crate::health::{Collector, ComponentState, HEALTH};
+use crate::ioc::{IocStore, IOC_TACTIC, IOC_TECHNIQUE};
+use crate::mitre::{self, TechniqueMatcher};
+use crate::ratelimit::RateLimiter;
+use crate::telemetry::{Event, EventContext, EventEmitter, EventType};
 
 // Critical kernel providers for EDR monitoring
 const KERNEL_PROCESS_PROVIDER: GUID = GUID::from_u128(0x22fb2cd6_0e7b_422b_a0c7_2fad1fd0e716);
 const KERNEL_FILE_PROVIDER: GUID = GUID::from_u128(0xedd08927_9cc4_4e65_b970_c2560fb5c289);
 const KERNEL_NETWORK_PROVIDER: GUID = GUID::from_u128(0x7dd42a49_5329_4832_8dfd_43d979153a88);
+const KERNEL_REGISTRY_PROVIDER: GUID = GUID::from_u128(0x70eb4f03_c1de_4f73_a051_33d13d5413bd);
+
+// Microsoft-Windows-Kernel-Process event Ids
+const PROCESS_START_EVENT_ID: u16 = 1;
+const PROCESS_STOP_EVENT_ID: u16 = 2;
+
+// Microsoft-Windows-Kernel-Network event Ids. Connect is an outbound TCP
+// connection established by a local process, accept an inbound one.
+// The v4 and v6 variants share a layout apart from the address width.
+const TCPV4_CONNECT_EVENT_ID: u16 = 12;
+const TCPV4_ACCEPT_EVENT_ID: u16 = 15;
+const TCPV6_CONNECT_EVENT_ID: u16 = 28;
+const TCPV6_ACCEPT_EVENT_ID: u16 = 31;
+
+// Microsoft-Windows-Kernel-Registry event Ids. Reads, opens and deletes are
+// not reported; creating a key or setting a value is what persistence needs.
+const REGISTRY_CREATE_KEY_EVENT_ID: u16 = 1;
+const REGISTRY_SET_VALUE_EVENT_ID: u16 = 5;
+
+/// CreateKey Disposition when the key already existed (nothing was created).
+const REG_OPENED_EXISTING_KEY: u32 = 2;
+
+/// OpenTrace returns this (INVALID_PROCESSTRACE_HANDLE) on failure.
+const INVALID_TRACE_HANDLE: u64 = u64::MAX;
+
+/// FILETIME value (100ns ticks since 1601-01-01) of the Unix epoch.
+const FILETIME_UNIX_EPOCH: i64 = 116_444_736_000_000_000;
 
 pub struct EtwConsumer {
     session_name: String,
     event_tx: mpsc::Sender<Event>,
     config: AgentConfig,
     dlp_engine: Arc<DlpEngine>,
-    session_handle: u64,
+    iocs: Arc<IocStore>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    session_handle: CONTROLTRACE_HANDLE,
+}
+
+/// State shared with `event_callback` through `EVENT_RECORD.UserContext`.
+/// Lives on the consumer thread's stack for the duration of ProcessTrace.
+struct CallbackContext {
+    events: EventEmitter,
+    #[allow(dead_code)] // Reserved for DLP scanning of file events
+    dlp_engine: Arc<DlpEngine>,
+    event_context: EventContext,
+    techniques: TechniqueMatcher,
+    iocs: Arc<IocStore>,
+}
+
+/// Header fields needed to route and timestamp an ETW event.
+#[derive(Debug, Clone, PartialEq)]
+struct RecordHeader {
+    provider_id: GUID,
+    event_id: u16,
+    version: u8,
+    opcode: u8,
+    process_id: u32,
+    thread_id: u32,
+    timestamp_ms: i64,
+}
+
+/// Direction of a TCP connection relative to this host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnDirection {
+    Outbound,
+    Inbound,
+}
+
+impl ConnDirection {
+    fn as_str(self) -> &'static str {
+        match self {
+            ConnDirection::Outbound => "outbound",
+            ConnDirection::Inbound => "inbound",
+        }
+    }
+}
+
+/// Decoded Microsoft-Windows-Kernel-Network TCP connect/accept payload.
+#[derive(Debug, Clone, PartialEq)]
+struct NetworkConnInfo {
+    pid: u32,
+    direction: ConnDirection,
+    local_addr: IpAddr,
+    local_port: u16,
+    remote_addr: IpAddr,
+    remote_port: u16,
+}
+
+/// Registry write reported by Microsoft-Windows-Kernel-Registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegistryOperation {
+    CreateKey,
+    SetValue,
+}
+
+impl RegistryOperation {
+    fn as_str(self) -> &'static str {
+        match self {
+            RegistryOperation::CreateKey => "create_key",
+            RegistryOperation::SetValue => "set_value",
+        }
+    }
+}
+
+/// Decoded Microsoft-Windows-Kernel-Registry CreateKey/SetValueKey payload.
+#[derive(Debug, Clone, PartialEq)]
+struct RegistryModifyInfo {
+    operation: RegistryOperation,
+    /// NTSTATUS of the operation; failed writes changed nothing.
+    status: u32,
+    /// CreateKey only: whether the key was created or merely opened.
+    created: bool,
+    key_path: String,
+    /// SetValue only.
+    value_name: Option<String>,
+}
+
+impl RegistryModifyInfo {
+    fn modified(&self) -> bool {
+        self.status == 0 && (self.operation == RegistryOperation::SetValue || self.created)
+    }
+}
+
+/// Decoded Microsoft-Windows-Kernel-Process ProcessStop payload.
+#[derive(Debug, Clone, PartialEq)]
+struct ProcessStopInfo {
+    pid: u32,
+    create_time_ms: i64,
+    exit_time_ms: i64,
+    exit_code: u32,
+}
+
+/// Decoded Microsoft-Windows-Kernel-Process ProcessStart payload.
+#[derive(Debug, Clone, PartialEq)]
+struct ProcessStartInfo {
+    pid: u32,
+    ppid: u32,
+    session_id: u32,
+    create_time_ms: i64,
+    image_name: String,
 }
 
 impl EtwConsumer {
@@ -37,65 +177,38 @@ impl EtwConsumer {
         event_tx: mpsc::Sender<Event>,
         config: AgentConfig,
         dlp_engine: Arc<DlpEngine>,
+        iocs: Arc<IocStore>,
     ) -> Self {
         Self {
             session_name: format!("SentinelEDR-{}", config.agent_id),
             event_tx,
             config,
             dlp_engine,
-            session_handle: 0,
+            iocs,
+            rate_limiter: None,
+            session_handle: CONTROLTRACE_HANDLE::default(),
         }
     }
 
+    /// Sample emitted events through the agent-wide `limiter`.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
     /// Initialize ETW session and subscribe to kernel providers.
-    /// This is a skeleton implementation - full callback logic not yet implemented.
     pub fn start(&mut self) -> Result<()> {
         info!("Initializing ETW consumer session: {}", self.session_name);
 
-        unsafe {
-            // Allocate EVENT_TRACE_PROPERTIES structure
-            // Size must include the session name string
-            let properties_size = std::mem::size_of::<EVENT_TRACE_PROPERTIES>()
-                + (self.session_name.len() + 1) * 2; // Wide string
-
-            let mut properties_buffer = vec![0u8; properties_size];
-            let properties = properties_buffer.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES;
-
-            (*properties).Wnode.BufferSize = properties_size as u32;
-            (*properties).Wnode.Flags = WNODE_FLAG_TRACED_GUID;
-            (*properties).Wnode.ClientContext = 1; // QPC clock resolution
-            (*properties).LogFileMode = EVENT_TRACE_REAL_TIME_MODE;
-            (*properties).LoggerNameOffset = std::mem::size_of::<EVENT_TRACE_PROPERTIES>() as u32;
-
-            // Start trace session
-            let session_name_wide: Vec<u16> = self.session_name
-                .encode_utf16()
-                .chain(std::iter::once(0))
-                .collect();
-
-            let mut session_handle: u64 = 0;
-
-            let result = StartTraceW(
-                &mut session_handle,
-                PCWSTR(session_name_wide.as_ptr()),
-                properties,
-            );
-
-            if result != 0 && result != ERROR_ALREADY_EXISTS.0 {
-                return Err(anyhow::anyhow!(
-                    "Failed to start ETW trace session: error code {}",
-                    result
-                ));
-            }
-
-            if result == ERROR_ALREADY_EXISTS.0 {
-                warn!("ETW session already exists, attempting to control existing session");
-                // In production, you would call ControlTrace to stop and restart
-            }
+        let session_handle = start_session(
+            || unsafe { self.start_trace() },
+            || unsafe { self.control_stop(CONTROLTRACE_HANDLE::default()) },
+        )?;
 
-            self.session_handle = session_handle;
-            info!("ETW session started with handle: 0x{:X}", session_handle);
+        self.session_handle = session_handle;
+        info!("ETW session started with handle: 0x{:X}", session_handle.Value);
 
+        unsafe {
             // Enable process monitoring provider
             self.enable_provider(
                 &KERNEL_PROCESS_PROVIDER,
@@ -114,19 +227,74 @@ impl EtwConsumer {
                 "Microsoft-Windows-Kernel-Network",
             )?;
 
+            // Enable registry monitoring provider (persistence via autostart keys)
+            self.enable_provider(
+                &KERNEL_REGISTRY_PROVIDER,
+                "Microsoft-Windows-Kernel-Registry",
+            )?;
+
             info!("All ETW providers enabled successfully");
         }
 
-        // TODO: Implement event callback processing loop
-        // This would involve:
-        // 1. OpenTrace with EVENT_TRACE_LOGFILE structure
-        // 2. Set EventRecordCallback to process_event_record
-        // 3. ProcessTrace in a loop
-        // 4. Parse event payloads and send to event_tx channel
-
         Ok(())
     }
 
+    /// Issue a single StartTraceW call for the real-time session.
+    unsafe fn start_trace(&self) -> windows::core::Result<CONTROLTRACE_HANDLE> {
+        // Allocate EVENT_TRACE_PROPERTIES structure
+        // Size must include the session name string
+        let properties_size = std::mem::size_of::<EVENT_TRACE_PROPERTIES>()
+            + (self.session_name.len() + 1) * 2; // Wide string
+
+        let mut properties_buffer = vec![0u8; properties_size];
+        let properties = properties_buffer.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES;
+
+        (*properties).Wnode.BufferSize = properties_size as u32;
+        (*properties).Wnode.Flags = WNODE_FLAG_TRACED_GUID;
+        (*properties).Wnode.ClientContext = 1; // QPC clock resolution
+        (*properties).LogFileMode = EVENT_TRACE_REAL_TIME_MODE;
+        (*properties).LoggerNameOffset = std::mem::size_of::<EVENT_TRACE_PROPERTIES>() as u32;
+
+        let session_name_wide = self.session_name_wide();
+        let mut session_handle = CONTROLTRACE_HANDLE::default();
+
+        StartTraceW(
+            &mut session_handle,
+            PCWSTR(session_name_wide.as_ptr()),
+            properties,
+        )?;
+
+        Ok(session_handle)
+    }
+
+    /// Stop the named session. A zero handle stops it by name, which is how
+    /// a stale session left behind by a crashed agent is torn down.
+    unsafe fn control_stop(&self, handle: CONTROLTRACE_HANDLE) -> windows::core::Result<()> {
+        let properties_size = std::mem::size_of::<EVENT_TRACE_PROPERTIES>()
+            + (self.session_name.len() + 1) * 2;
+
+        let mut properties_buffer = vec![0u8; properties_size];
+        let properties = properties_buffer.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES;
+
+        (*properties).Wnode.BufferSize = properties_size as u32;
+
+        let session_name_wide = self.session_name_wide();
+
+        ControlTraceW(
+            handle,
+            PCWSTR(session_name_wide.as_ptr()),
+            properties,
+            EVENT_TRACE_CONTROL_STOP,
+        )
+    }
+
+    fn session_name_wide(&self) -> Vec<u16> {
+        self.session_name
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect()
+    }
+
     /// Enable a specific ETW provider for the trace session.
     unsafe fn enable_provider(&self, provider_guid: &GUID, provider_name: &str) -> Result<()> {
         debug!("Enabling ETW provider: {}", provider_name);
@@ -134,19 +302,19 @@ impl EtwConsumer {
         let result = EnableTraceEx2(
             self.session_handle,
             provider_guid,
-            EVENT_CONTROL_CODE_ENABLE_PROVIDER,
-            TRACE_LEVEL_INFORMATION.0 as u8,
+            EVENT_CONTROL_CODE_ENABLE_PROVIDER.0,
+            TRACE_LEVEL_INFORMATION as u8,
             0xFFFFFFFFFFFFFFFF, // Match any keyword
             0,
             0,
             None,
         );
 
-        if result != 0 {
+        if let Err(e) = result {
             return Err(anyhow::anyhow!(
-                "Failed to enable provider {}: error code {}",
+                "Failed to enable provider {}: {}",
                 provider_name,
-                result
+                e
             ));
         }
 
@@ -154,90 +322,917 @@ impl EtwConsumer {
         Ok(())
     }
 
-    /// Callback invoked for each ETW event (skeleton - not yet fully implemented).
-    /// In the full implementation, this would:
-    /// - Parse EVENT_RECORD structure
-    /// - Extract process/file/network details
-    /// - Apply MITRE ATT&CK mapping
-    /// - Send Event to channel for gRPC transmission
-    #[allow(dead_code)]
+    /// Consume events from the real-time session until `shutdown` is cancelled.
+    ///
+    /// Opens the session with OpenTrace and blocks in ProcessTrace, which
+    /// invokes `event_callback` for every record. A watcher thread calls
+    /// CloseTrace on shutdown so ProcessTrace returns after delivering any
+    /// buffered events.
+    pub fn process_events(&self, shutdown: &CancellationToken) -> Result<()> {
+        let mut logger_name = self.session_name_wide();
+
+        let mut events = EventEmitter::new(self.event_tx.clone(), "ETW");
+        if let Some(limiter) = &self.rate_limiter {
+            events = events.with_rate_limiter(limiter.clone());
+        }
+        let context = CallbackContext {
+            events,
+            dlp_engine: self.dlp_engine.clone(),
+            event_context: EventContext::from_config(&self.config),
+            techniques: TechniqueMatcher::new(),
+            iocs: self.iocs.clone(),
+        };
+
+        // SAFETY: EVENT_TRACE_LOGFILEW is a plain C struct; all-zero is its documented initial state
+        let mut logfile: EVENT_TRACE_LOGFILEW = unsafe { std::mem::zeroed() };
+        logfile.LoggerName = PWSTR(logger_name.as_mut_ptr());
+        logfile.Anonymous1.ProcessTraceMode = PROCESS_TRACE_MODE_REAL_TIME | PROCESS_TRACE_MODE_EVENT_RECORD;
+        logfile.Anonymous2.EventRecordCallback = Some(Self::event_callback);
+        logfile.Context = &context as *const CallbackContext as *mut c_void;
+
+        let trace_handle = unsafe { OpenTraceW(&mut logfile) };
+        if trace_handle.Value == INVALID_TRACE_HANDLE {
+            return Err(anyhow::anyhow!(
+                "Failed to open ETW trace {}: {}",
+                self.session_name,
+                Error::from_win32()
+            ));
+        }
+
+        // Closes the trace on shutdown (or once ProcessTrace returns on its own)
+        let session_done = shutdown.child_token();
+        let watcher = {
+            let session_done = session_done.clone();
+            std::thread::spawn(move || {
+                while !session_done.is_cancelled() {
+                    std::thread::sleep(std::time::Duration::from_millis(250));
+                }
+                // SAFETY: handle came from OpenTraceW and is closed exactly once, here
+                unsafe {
+                    let _ = CloseTrace(trace_handle);
+                }
+            })
+        };
+
+        info!("ETW consumer processing events for session {}", self.session_name);
+        // Blocks until the trace is closed or the session stops
+        let result = unsafe { ProcessTrace(&[trace_handle], None, None) };
+
+        session_done.cancel();
+        let _ = watcher.join();
+
+        match result {
+            Err(e) if !shutdown.is_cancelled() => {
+                Err(anyhow::anyhow!("ETW ProcessTrace failed: {}", e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Callback invoked by ProcessTrace for each ETW event.
+    /// Parses the EVENT_RECORD, converts supported events into `Event`s and
+    /// hands them to the telemetry channel without blocking the ETW thread.
     unsafe extern "system" fn event_callback(event_record: *mut EVENT_RECORD) {
         if event_record.is_null() {
             return;
         }
 
-        // TODO: Implement full event parsing logic
-        // Example structure access:
-        // let record = &*event_record;
-        // let event_id = record.EventHeader.EventDescriptor.Id;
-        // let process_id = record.EventHeader.ProcessId;
+        let record = &*event_record;
+        if record.UserContext.is_null() {
+            return;
+        }
+        let context = &*(record.UserContext as *const CallbackContext);
+
+        let header = parse_record_header(record);
 
-        debug!("ETW event received (callback skeleton)");
+        let data = user_data(record);
+
+        let event = if header.provider_id == KERNEL_PROCESS_PROVIDER {
+            match header.event_id {
+                PROCESS_START_EVENT_ID => parse_process_start(header.version, data)
+                    .map(|info| process_start_event(context, &header, &info)),
+                PROCESS_STOP_EVENT_ID => parse_process_stop(data)
+                    .map(|info| process_stop_event(context, &header, &info)),
+                _ => return,
+            }
+        } else if header.provider_id == KERNEL_NETWORK_PROVIDER {
+            match header.event_id {
+                TCPV4_CONNECT_EVENT_ID | TCPV4_ACCEPT_EVENT_ID
+                | TCPV6_CONNECT_EVENT_ID | TCPV6_ACCEPT_EVENT_ID => {
+                    parse_tcp_connection(header.event_id, data)
+                        .map(|info| network_conn_event(context, &header, &info))
+                }
+                _ => return,
+            }
+        } else if header.provider_id == KERNEL_REGISTRY_PROVIDER {
+            match header.event_id {
+                REGISTRY_CREATE_KEY_EVENT_ID | REGISTRY_SET_VALUE_EVENT_ID => {
+                    let pointer_size = if u32::from(record.EventHeader.Flags) & EVENT_HEADER_FLAG_32_BIT_HEADER != 0 {
+                        4
+                    } else {
+                        8
+                    };
+                    match parse_registry_event(header.event_id, pointer_size, data) {
+                        Some(info) if !info.modified() => return,
+                        info => info.map(|info| registry_modify_event(context, &header, &info)),
+                    }
+                }
+                _ => return,
+            }
+        } else {
+            return;
+        };
+
+        match event {
+            Some(event) => {
+                context.events.emit_event(event);
+            }
+            None => debug!("Malformed payload for event {} from pid {}", header.event_id, header.process_id),
+        }
     }
 
     /// Stop the ETW trace session and clean up resources.
     pub fn stop(&mut self) -> Result<()> {
-        if self.session_handle == 0 {
+        if self.session_handle.Value == 0 {
             return Ok(());
         }
 
         info!("Stopping ETW session: {}", self.session_name);
 
-        unsafe {
-            let properties_size = std::mem::size_of::<EVENT_TRACE_PROPERTIES>()
-                + (self.session_name.len() + 1) * 2;
+        match unsafe { self.control_stop(self.session_handle) } {
+            Ok(()) => info!("ETW session stopped successfully"),
+            Err(e) => warn!("Failed to stop ETW session: {}", e),
+        }
+
+        self.session_handle = CONTROLTRACE_HANDLE::default();
+        Ok(())
+    }
+}
+
+impl Drop for EtwConsumer {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// Start the trace session, recovering from a stale session of the same name.
+///
+/// A force-killed agent leaves its named session running, so StartTrace fails
+/// with ERROR_ALREADY_EXISTS. In that case the old session is stopped and the
+/// start retried once; any other failure, or a failed retry, is an error.
+fn start_session<S, T>(mut start: S, stop_existing: T) -> Result<CONTROLTRACE_HANDLE>
+where
+    S: FnMut() -> windows::core::Result<CONTROLTRACE_HANDLE>,
+    T: FnOnce() -> windows::core::Result<()>,
+{
+    match start() {
+        Ok(handle) => Ok(handle),
+        Err(e) if e.code() == ERROR_ALREADY_EXISTS.to_hresult() => {
+            warn!("ETW session already exists, stopping stale session and retrying");
+            if let Err(e) = stop_existing() {
+                return Err(anyhow::anyhow!("Failed to stop stale ETW session: {}", e));
+            }
+            start().map_err(|e| {
+                anyhow::anyhow!("Failed to restart ETW trace session: {}", e)
+            })
+        }
+        Err(e) => Err(anyhow::anyhow!("Failed to start ETW trace session: {}", e)),
+    }
+}
+
+/// Extract the routing fields from an EVENT_RECORD header.
+fn parse_record_header(record: &EVENT_RECORD) -> RecordHeader {
+    let header = &record.EventHeader;
+    RecordHeader {
+        provider_id: header.ProviderId,
+        event_id: header.EventDescriptor.Id,
+        version: header.EventDescriptor.Version,
+        opcode: header.EventDescriptor.Opcode,
+        process_id: header.ProcessId,
+        thread_id: header.ThreadId,
+        timestamp_ms: filetime_to_unix_millis(header.TimeStamp),
+    }
+}
+
+/// View the event's UserData payload as a byte slice.
+///
+/// # Safety
+/// `record.UserData` must point to `UserDataLength` readable bytes, which ETW
+/// guarantees for the duration of the callback.
+unsafe fn user_data(record: &EVENT_RECORD) -> &[u8] {
+    if record.UserData.is_null() || record.UserDataLength == 0 {
+        return &[];
+    }
+    std::slice::from_raw_parts(record.UserData as *const u8, record.UserDataLength as usize)
+}
+
+/// Decode a Kernel-Process ProcessStart payload.
+///
+/// Versions 0-2: ProcessID u32, CreateTime FILETIME, ParentProcessID u32,
+/// SessionID u32, [Flags u32 in version >= 1], ImageName (NUL-terminated
+/// UTF-16); version 2 appends fields after the image name.
+///
+/// Version 3 (current Windows 10/11): ProcessID u32, ProcessSequenceNumber
+/// u64, CreateTime FILETIME, ParentProcessID u32, ParentProcessSequenceNumber
+/// u64, SessionID u32, Flags u32, ProcessTokenElevationType u32,
+/// ProcessTokenIsElevated u32, MandatoryLabel SID, ImageName, followed by
+/// fields we ignore. Later versions only append, so they parse as version 3.
+fn parse_process_start(version: u8, data: &[u8]) -> Option<ProcessStartInfo> {
+    let pid = read_u32(data, 0)?;
+    let (create_time, ppid, session_id, name_offset) = match version {
+        0 => (read_u64(data, 4)?, read_u32(data, 12)?, read_u32(data, 16)?, 20),
+        1 | 2 => (read_u64(data, 4)?, read_u32(data, 12)?, read_u32(data, 16)?, 24),
+        _ => (read_u64(data, 12)?, read_u32(data, 20)?, read_u32(data, 32)?, 48 + sid_len(data, 48)?),
+    };
+    let create_time = create_time as i64;
+    let image_name = read_utf16z(data, name_offset)?;
+
+    Some(ProcessStartInfo {
+        pid,
+        ppid,
+        session_id,
+        create_time_ms: filetime_to_unix_millis(create_time),
+        image_name,
+    })
+}
+
+/// Length of the SID at `offset`: 8 header bytes plus one u32 per sub-authority.
+fn sid_len(data: &[u8], offset: usize) -> Option<usize> {
+    let sub_authorities = *data.get(offset + 1)? as usize;
+    let len = 8 + 4 * sub_authorities;
+    data.get(offset..offset + len)?;
+    Some(len)
+}
+
+/// Decode a Kernel-Process ProcessStop payload.
+///
+/// Layout: ProcessID u32, CreateTime FILETIME, ExitTime FILETIME,
+/// ExitCode u32, followed by resource counters and the image name, which
+/// are not needed (the start event already carried the image).
+fn parse_process_stop(data: &[u8]) -> Option<ProcessStopInfo> {
+    Some(ProcessStopInfo {
+        pid: read_u32(data, 0)?,
+        create_time_ms: filetime_to_unix_millis(read_u64(data, 4)? as i64),
+        exit_time_ms: filetime_to_unix_millis(read_u64(data, 12)? as i64),
+        exit_code: read_u32(data, 20)?,
+    })
+}
+
+/// Build the telemetry event for a process exit.
+fn process_stop_event(context: &CallbackContext, header: &RecordHeader, info: &ProcessStopInfo) -> Event {
+    // pid + create_time match the earlier ProcessStart payload
+    let payload = serde_json::json!({
+        "pid": info.pid,
+        "create_time": info.create_time_ms,
+        "exit_time": info.exit_time_ms,
+        "exit_code": info.exit_code,
+    });
+
+    // An exit is not itself an ATT&CK tactic
+    let mut event = Event::new(&context.event_context, EventType::ProcessTerminate, String::new(), payload.to_string());
+    event.timestamp = header.timestamp_ms;
+    event
+}
+
+/// Build the telemetry event for a process start.
+fn process_start_event(context: &CallbackContext, header: &RecordHeader, info: &ProcessStartInfo) -> Event {
+    let payload = serde_json::json!({
+        "pid": info.pid,
+        "ppid": info.ppid,
+        "session_id": info.session_id,
+        "image": info.image_name,
+        "create_time": info.create_time_ms,
+    });
+
+    // The start payload carries no command line; classify on the image alone
+    let (tactic, technique) = context.techniques
+        .infer(&info.image_name, "")
+        .unwrap_or(("TA0002_Execution", ""));
+
+    let mut event = Event::new(
+        &context.event_context,
+        EventType::ProcessStart,
+        tactic.to_string(),
+        payload.to_string(),
+    );
+    event.mitre_technique = technique.to_string();
+    event.timestamp = header.timestamp_ms;
+    event
+}
 
-            let mut properties_buffer = vec![0u8; properties_size];
-            let properties = properties_buffer.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES;
+/// Decode a Kernel-Network TCP connect/accept payload for the given event Id.
+///
+/// Layout: PID u32, size u32, daddr, saddr, dport u16, sport u16, followed by
+/// TCP options we ignore. Addresses are 4 bytes (v4) or 16 bytes (v6) and,
+/// like the ports, are in network byte order. daddr/dport is always the peer.
+fn parse_tcp_connection(event_id: u16, data: &[u8]) -> Option<NetworkConnInfo> {
+    let (direction, addr_len) = match event_id {
+        TCPV4_CONNECT_EVENT_ID => (ConnDirection::Outbound, 4),
+        TCPV4_ACCEPT_EVENT_ID => (ConnDirection::Inbound, 4),
+        TCPV6_CONNECT_EVENT_ID => (ConnDirection::Outbound, 16),
+        TCPV6_ACCEPT_EVENT_ID => (ConnDirection::Inbound, 16),
+        _ => return None,
+    };
 
-            (*properties).Wnode.BufferSize = properties_size as u32;
+    let pid = read_u32(data, 0)?;
+    let remote_addr = read_ip(data, 8, addr_len)?;
+    let local_addr = read_ip(data, 8 + addr_len, addr_len)?;
+    let ports_offset = 8 + 2 * addr_len;
+    let remote_port = read_u16_be(data, ports_offset)?;
+    let local_port = read_u16_be(data, ports_offset + 2)?;
 
-            let session_name_wide: Vec<u16> = self.session_name
-                .encode_utf16()
-                .chain(std::iter::once(0))
-                .collect();
+    Some(NetworkConnInfo {
+        pid,
+        direction,
+        local_addr,
+        local_port,
+        remote_addr,
+        remote_port,
+    })
+}
+
+/// Build the telemetry event for a TCP connect/accept.
+fn network_conn_event(context: &CallbackContext, header: &RecordHeader, info: &NetworkConnInfo) -> Event {
+    let mut payload = serde_json::json!({
+        "pid": info.pid,
+        "protocol": "tcp",
+        "direction": info.direction.as_str(),
+        "local_addr": info.local_addr.to_string(),
+        "local_port": info.local_port,
+        "remote_addr": info.remote_addr.to_string(),
+        "remote_port": info.remote_port,
+    });
 
-            let result = ControlTraceW(
-                self.session_handle,
-                PCWSTR(session_name_wide.as_ptr()),
-                properties,
-                EVENT_TRACE_CONTROL_STOP,
-            );
+    // Direction alone says nothing about intent; only an IOC hit is C2
+    let mut event = Event::new(&context.event_context, EventType::NetworkConn, String::new(), String::new());
+    // The peer is the remote end in both directions
+    if let Some(ioc_id) = context.iocs.lookup_ip(info.remote_addr) {
+        payload["ioc_id"] = ioc_id.into();
+        event.mitre_tactic = IOC_TACTIC.to_string();
+        event.mitre_technique = IOC_TECHNIQUE.to_string();
+        event.severity = Severity::High.into();
+    }
+    event.payload = payload.to_string();
+    event.timestamp = header.timestamp_ms;
+    event
+}
 
-            if result != 0 {
-                warn!("Failed to stop ETW session: error code {}", result);
+/// Decode a Kernel-Registry CreateKey or SetValueKey payload.
+///
+/// CreateKey layout: BaseObject ptr, KeyObject ptr, Status u32,
+/// Disposition u32, BaseName, RelativeName. SetValueKey layout: KeyObject
+/// ptr, Status u32, Type u32, DataSize u32, KeyName, ValueName, followed by
+/// captured data we ignore. Names are NUL-terminated UTF-16; pointers are
+/// `pointer_size` bytes wide.
+fn parse_registry_event(event_id: u16, pointer_size: usize, data: &[u8]) -> Option<RegistryModifyInfo> {
+    match event_id {
+        REGISTRY_CREATE_KEY_EVENT_ID => {
+            let status = read_u32(data, 2 * pointer_size)?;
+            let disposition = read_u32(data, 2 * pointer_size + 4)?;
+            let (base_name, next) = read_utf16z_at(data, 2 * pointer_size + 8)?;
+            let (relative_name, _) = read_utf16z_at(data, next)?;
+            // RelativeName is relative to BaseName when a base key was given
+            let key_path = if base_name.is_empty() {
+                relative_name
             } else {
-                info!("ETW session stopped successfully");
-            }
+                format!("{}\\{}", base_name.trim_end_matches('\\'), relative_name)
+            };
+
+            Some(RegistryModifyInfo {
+                operation: RegistryOperation::CreateKey,
+                status,
+                created: disposition != REG_OPENED_EXISTING_KEY,
+                key_path,
+                value_name: None,
+            })
         }
+        REGISTRY_SET_VALUE_EVENT_ID => {
+            let status = read_u32(data, pointer_size)?;
+            let (key_path, next) = read_utf16z_at(data, pointer_size + 12)?;
+            let (value_name, _) = read_utf16z_at(data, next)?;
 
-        self.session_handle = 0;
-        Ok(())
+            Some(RegistryModifyInfo {
+                operation: RegistryOperation::SetValue,
+                status,
+                created: false,
+                key_path,
+                value_name: Some(value_name),
+            })
+        }
+        _ => None,
     }
 }
 
-impl Drop for EtwConsumer {
-    fn drop(&mut self) {
-        let _ = self.stop();
+/// Build the telemetry event for a registry write. Writes to autostart
+/// locations are tagged as persistence and raised to Medium severity.
+fn registry_modify_event(context: &CallbackContext, header: &RecordHeader, info: &RegistryModifyInfo) -> Event {
+    let mut payload = serde_json::json!({
+        "pid": header.process_id,
+        "operation": info.operation.as_str(),
+        "key": info.key_path,
+    });
+    if let Some(value_name) = &info.value_name {
+        payload["value_name"] = value_name.as_str().into();
+    }
+
+    let technique = mitre::autostart_technique(&info.key_path);
+    let tactic = if technique.is_some() { mitre::PERSISTENCE_TACTIC } else { "" };
+
+    let mut event = Event::new(
+        &context.event_context,
+        EventType::RegistryModify,
+        tactic.to_string(),
+        payload.to_string(),
+    );
+    if let Some(technique) = technique {
+        event.mitre_technique = technique.to_string();
+        event.severity = Severity::Medium.into();
     }
+    event.timestamp = header.timestamp_ms;
+    event
+}
+
+/// Convert a FILETIME tick count to Unix epoch milliseconds.
+fn filetime_to_unix_millis(filetime: i64) -> i64 {
+    (filetime - FILETIME_UNIX_EPOCH) / 10_000
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u16_be(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// Read a network-order IPv4 (4 bytes) or IPv6 (16 bytes) address.
+fn read_ip(data: &[u8], offset: usize, len: usize) -> Option<IpAddr> {
+    let bytes = data.get(offset..offset + len)?;
+    match len {
+        4 => Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?))),
+        16 => Some(IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?))),
+        _ => None,
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Read a NUL-terminated little-endian UTF-16 string starting at `offset`.
+fn read_utf16z(data: &[u8], offset: usize) -> Option<String> {
+    read_utf16z_at(data, offset).map(|(string, _)| string)
+}
+
+/// Like `read_utf16z`, also returning the offset just past the terminator
+/// (or the end of `data` if there is none), where the next field starts.
+fn read_utf16z_at(data: &[u8], offset: usize) -> Option<(String, usize)> {
+    let units: Vec<u16> = data.get(offset..)?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    let next = (offset + 2 * (units.len() + 1)).min(data.len());
+    Some((String::from_utf16_lossy(&units), next))
 }
 
 /// Entry point called from main.rs to start ETW monitoring.
 /// Runs in a blocking thread to avoid blocking the tokio runtime.
+/// Returns once `shutdown` is cancelled, after the trace session is stopped.
 pub fn start_consumer(
     event_tx: mpsc::Sender<Event>,
     config: AgentConfig,
     dlp_engine: Arc<DlpEngine>,
+    iocs: Arc<IocStore>,
+    rate_limiter: Arc<RateLimiter>,
+    shutdown: CancellationToken,
 ) -> Result<()> {
-    let mut consumer = EtwConsumer::new(event_tx, config, dlp_engine);
-    consumer.start()?;
+    HEALTH.set_collector(Collector::Etw, ComponentState::Starting);
+    let mut consumer = EtwConsumer::new(event_tx, config, dlp_engine, iocs).with_rate_limiter(rate_limiter);
+    if let Err(e) = consumer.start() {
+        HEALTH.set_collector(Collector::Etw, ComponentState::Failed);
+        return Err(e);
+    }
+    HEALTH.set_collector(Collector::Etw, ComponentState::Running);
+
+    let result = consumer.process_events(&shutdown);
+    HEALTH.collector_finished(Collector::Etw, &result);
+
+    info!("ETW event processing finished, stopping consumer");
+    consumer.stop()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNIX_MS: i64 = 1_700_000_000_123;
+
+    /// ProcessStart payload in the manifest layout of `version` (0, 1 or 3).
+    fn process_start_payload(version: u8, image: &str) -> Vec<u8> {
+        let create_time = FILETIME_UNIX_EPOCH + UNIX_MS * 10_000;
+        let mut data = Vec::new();
+        data.extend_from_slice(&4242u32.to_le_bytes());
+        if version >= 3 {
+            data.extend_from_slice(&0x0000_0001_0000_3a2bu64.to_le_bytes()); // ProcessSequenceNumber
+            data.extend_from_slice(&create_time.to_le_bytes());
+            data.extend_from_slice(&600u32.to_le_bytes());
+            data.extend_from_slice(&0x0000_0001_0000_0102u64.to_le_bytes()); // ParentProcessSequenceNumber
+            data.extend_from_slice(&1u32.to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes()); // Flags
+            data.extend_from_slice(&3u32.to_le_bytes()); // TokenElevationTypeLimited
+            data.extend_from_slice(&0u32.to_le_bytes()); // ProcessTokenIsElevated
+            // MandatoryLabel S-1-16-8192 (medium integrity)
+            data.extend_from_slice(&[1, 1, 0, 0, 0, 0, 0, 16]);
+            data.extend_from_slice(&0x2000u32.to_le_bytes());
+        } else {
+            data.extend_from_slice(&create_time.to_le_bytes());
+            data.extend_from_slice(&600u32.to_le_bytes());
+            data.extend_from_slice(&1u32.to_le_bytes());
+            if version >= 1 {
+                data.extend_from_slice(&0u32.to_le_bytes()); // Flags
+            }
+        }
+        push_utf16z(&mut data, image);
+        if version >= 3 {
+            data.extend_from_slice(&0x0001_f00du32.to_le_bytes()); // ImageChecksum
+            data.extend_from_slice(&0x5f00_0000u32.to_le_bytes()); // TimeDateStamp
+            push_utf16z(&mut data, ""); // PackageFullName
+            push_utf16z(&mut data, ""); // PackageRelativeAppId
+        }
+        data
+    }
+
+    fn synthetic_record(payload: &mut [u8]) -> EVENT_RECORD {
+        // SAFETY: EVENT_RECORD is a plain C struct; zeroed is a valid starting point
+        let mut record: EVENT_RECORD = unsafe { std::mem::zeroed() };
+        record.EventHeader.ProviderId = KERNEL_PROCESS_PROVIDER;
+        record.EventHeader.EventDescriptor.Id = PROCESS_START_EVENT_ID;
+        record.EventHeader.EventDescriptor.Version = 3;
+        record.EventHeader.EventDescriptor.Opcode = 1;
+        record.EventHeader.ProcessId = 4;
+        record.EventHeader.ThreadId = 88;
+        record.EventHeader.TimeStamp = FILETIME_UNIX_EPOCH + UNIX_MS * 10_000;
+        record.UserData = payload.as_mut_ptr() as *mut c_void;
+        record.UserDataLength = payload.len() as u16;
+        record
+    }
+
+    #[test]
+    fn test_parse_record_header() {
+        let mut payload = process_start_payload(3, "cmd.exe");
+        let record = synthetic_record(&mut payload);
+
+        let header = parse_record_header(&record);
+        assert_eq!(header.provider_id, KERNEL_PROCESS_PROVIDER);
+        assert_eq!(header.event_id, PROCESS_START_EVENT_ID);
+        assert_eq!(header.version, 3);
+        assert_eq!(header.opcode, 1);
+        assert_eq!(header.process_id, 4);
+        assert_eq!(header.thread_id, 88);
+        assert_eq!(header.timestamp_ms, UNIX_MS);
+    }
+
+    #[test]
+    fn test_parse_process_start_versions() {
+        let v0 = parse_process_start(0, &process_start_payload(0, "notepad.exe")).unwrap();
+        let v1 = parse_process_start(1, &process_start_payload(1, "notepad.exe")).unwrap();
+        let v3 = parse_process_start(3, &process_start_payload(3, "notepad.exe")).unwrap();
+
+        for info in [v0, v1, v3] {
+            assert_eq!(info.pid, 4242);
+            assert_eq!(info.ppid, 600);
+            assert_eq!(info.session_id, 1);
+            assert_eq!(info.create_time_ms, UNIX_MS);
+            assert_eq!(info.image_name, "notepad.exe");
+        }
+
+        assert!(parse_process_start(1, &[0u8; 10]).is_none());
+        // A v3 payload cut off inside the MandatoryLabel SID
+        assert!(parse_process_start(3, &process_start_payload(3, "notepad.exe")[..52]).is_none());
+    }
+
+    fn process_stop_payload(exit_code: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&4242u32.to_le_bytes());
+        data.extend_from_slice(&(FILETIME_UNIX_EPOCH + UNIX_MS * 10_000).to_le_bytes());
+        data.extend_from_slice(&(FILETIME_UNIX_EPOCH + (UNIX_MS + 5_000) * 10_000).to_le_bytes());
+        data.extend_from_slice(&exit_code.to_le_bytes());
+        data.extend_from_slice(&[0u8; 32]); // counters we ignore
+        data
+    }
+
+    #[test]
+    fn test_parse_process_stop() {
+        let info = parse_process_stop(&process_stop_payload(0xC000_0005)).unwrap();
+        assert_eq!(
+            info,
+            ProcessStopInfo {
+                pid: 4242,
+                create_time_ms: UNIX_MS,
+                exit_time_ms: UNIX_MS + 5_000,
+                exit_code: 0xC000_0005,
+            }
+        );
+
+        assert!(parse_process_stop(&process_stop_payload(0)[..23]).is_none());
+    }
+
+    fn test_callback_context(event_tx: mpsc::Sender<Event>, iocs: Arc<IocStore>) -> CallbackContext {
+        CallbackContext {
+            events: EventEmitter::new(event_tx, "ETW"),
+            dlp_engine: Arc::new(DlpEngine::new()),
+            event_context: EventContext::new("agent-1".to_string(), "tenant-1".to_string()),
+            techniques: TechniqueMatcher::new(),
+            iocs,
+        }
+    }
+
+    #[test]
+    fn test_callback_emits_process_terminate_event() {
+        let (event_tx, mut event_rx) = mpsc::channel(4);
+        let context = test_callback_context(event_tx, Arc::new(IocStore::new()));
+
+        let mut payload = process_stop_payload(1);
+        let mut record = synthetic_record(&mut payload);
+        record.EventHeader.EventDescriptor.Id = PROCESS_STOP_EVENT_ID;
+        record.UserContext = &context as *const CallbackContext as *mut c_void;
 
-    // TODO: Replace this with actual event processing loop
-    // For now, this is a placeholder that keeps the thread alive
-    info!("ETW consumer thread running (event processing loop not yet implemented)");
+        unsafe { EtwConsumer::event_callback(&mut record) };
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::ProcessTerminate);
+        assert_eq!(event.mitre_tactic, "");
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["pid"], 4242);
+        assert_eq!(payload["exit_code"], 1);
+        assert_eq!(payload["create_time"], UNIX_MS);
+    }
+
+    #[test]
+    fn test_callback_emits_process_start_event() {
+        let (event_tx, mut event_rx) = mpsc::channel(4);
+        let context = test_callback_context(event_tx, Arc::new(IocStore::new()));
+
+        let mut payload = process_start_payload(3, "powershell.exe");
+        let mut record = synthetic_record(&mut payload);
+        record.UserContext = &context as *const CallbackContext as *mut c_void;
+
+        unsafe { EtwConsumer::event_callback(&mut record) };
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::ProcessStart);
+        assert_eq!(event.timestamp, UNIX_MS);
+        assert_eq!(event.tenant_id, "tenant-1");
+
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["pid"], 4242);
+        assert_eq!(payload["image"], "powershell.exe");
+    }
+
+    fn tcp_payload(pid: u32, remote: &[u8], local: &[u8], remote_port: u16, local_port: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&pid.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes()); // size
+        data.extend_from_slice(remote);
+        data.extend_from_slice(local);
+        data.extend_from_slice(&remote_port.to_be_bytes());
+        data.extend_from_slice(&local_port.to_be_bytes());
+        data.extend_from_slice(&[0u8; 24]); // mss, sackopt, tsopt, wsopt, rcvwin, ...
+        data
+    }
+
+    #[test]
+    fn test_parse_tcpv4_connect() {
+        let data = tcp_payload(1337, &[93, 184, 216, 34], &[10, 0, 0, 5], 443, 50123);
+
+        let info = parse_tcp_connection(TCPV4_CONNECT_EVENT_ID, &data).unwrap();
+        assert_eq!(info.pid, 1337);
+        assert_eq!(info.direction, ConnDirection::Outbound);
+        assert_eq!(info.remote_addr, "93.184.216.34".parse::<IpAddr>().unwrap());
+        assert_eq!(info.remote_port, 443);
+        assert_eq!(info.local_addr, "10.0.0.5".parse::<IpAddr>().unwrap());
+        assert_eq!(info.local_port, 50123);
+    }
+
+    #[test]
+    fn test_parse_tcpv6_accept() {
+        let remote: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let local: Ipv6Addr = "fe80::abcd:1234".parse().unwrap();
+        let data = tcp_payload(4, &remote.octets(), &local.octets(), 61000, 3389);
+
+        let info = parse_tcp_connection(TCPV6_ACCEPT_EVENT_ID, &data).unwrap();
+        assert_eq!(info.direction, ConnDirection::Inbound);
+        assert_eq!(info.remote_addr, IpAddr::V6(remote));
+        assert_eq!(info.local_addr, IpAddr::V6(local));
+        assert_eq!((info.remote_port, info.local_port), (61000, 3389));
+
+        // Truncated payloads and non-connection Ids are rejected
+        assert!(parse_tcp_connection(TCPV6_CONNECT_EVENT_ID, &data[..20]).is_none());
+        assert!(parse_tcp_connection(10, &data).is_none());
+    }
+
+    #[test]
+    fn test_callback_emits_network_conn_event() {
+        let (event_tx, mut event_rx) = mpsc::channel(4);
+        let context = test_callback_context(event_tx, Arc::new(IocStore::new()));
+
+        let mut payload = tcp_payload(1337, &[93, 184, 216, 34], &[10, 0, 0, 5], 443, 50123);
+        let mut record = synthetic_record(&mut payload);
+        record.EventHeader.ProviderId = KERNEL_NETWORK_PROVIDER;
+        record.EventHeader.EventDescriptor.Id = TCPV4_CONNECT_EVENT_ID;
+        record.UserContext = &context as *const CallbackContext as *mut c_void;
+
+        unsafe { EtwConsumer::event_callback(&mut record) };
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::NetworkConn);
+        assert_eq!(event.mitre_tactic, "");
+        assert_eq!(event.mitre_technique, "");
+
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["remote_addr"], "93.184.216.34");
+        assert_eq!(payload["remote_port"], 443);
+        assert_eq!(payload["direction"], "outbound");
+        assert!(payload.get("ioc_id").is_none());
+    }
+
+    #[test]
+    fn test_network_conn_to_ioc_is_flagged() {
+        let (event_tx, mut event_rx) = mpsc::channel(4);
+        let iocs = IocStore::from_indicators(vec![crate::ioc::Indicator {
+            id: "IOC-C2".to_string(),
+            value: "93.184.216.0/24".to_string(),
+        }])
+        .unwrap();
+        let context = test_callback_context(event_tx, Arc::new(iocs));
+
+        let mut payload = tcp_payload(1337, &[93, 184, 216, 34], &[10, 0, 0, 5], 443, 50123);
+        let mut record = synthetic_record(&mut payload);
+        record.EventHeader.ProviderId = KERNEL_NETWORK_PROVIDER;
+        record.EventHeader.EventDescriptor.Id = TCPV4_CONNECT_EVENT_ID;
+        record.UserContext = &context as *const CallbackContext as *mut c_void;
+
+        unsafe { EtwConsumer::event_callback(&mut record) };
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.severity, 3);
+        assert_eq!(event.mitre_tactic, IOC_TACTIC);
+        assert_eq!(event.mitre_technique, "T1071");
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["ioc_id"], "IOC-C2");
+    }
+
+    fn push_utf16z(data: &mut Vec<u8>, s: &str) {
+        for unit in s.encode_utf16().chain(std::iter::once(0)) {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+
+    fn set_value_payload(status: u32, key: &str, value_name: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&0xffff_a000_0000_1000u64.to_le_bytes()); // KeyObject
+        data.extend_from_slice(&status.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes()); // REG_SZ
+        data.extend_from_slice(&64u32.to_le_bytes());
+        push_utf16z(&mut data, key);
+        push_utf16z(&mut data, value_name);
+        data.extend_from_slice(&[0u8; 8]); // captured data we ignore
+        data
+    }
+
+    fn create_key_payload(disposition: u32, base: &str, relative: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&0u64.to_le_bytes()); // BaseObject
+        data.extend_from_slice(&0xffff_a000_0000_2000u64.to_le_bytes()); // KeyObject
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&disposition.to_le_bytes());
+        push_utf16z(&mut data, base);
+        push_utf16z(&mut data, relative);
+        data
+    }
+
+    const RUN_KEY: &str = "\\REGISTRY\\MACHINE\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run";
+
+    #[test]
+    fn test_parse_registry_events() {
+        let info = parse_registry_event(REGISTRY_SET_VALUE_EVENT_ID, 8, &set_value_payload(0, RUN_KEY, "Updater")).unwrap();
+        assert_eq!(
+            info,
+            RegistryModifyInfo {
+                operation: RegistryOperation::SetValue,
+                status: 0,
+                created: false,
+                key_path: RUN_KEY.to_string(),
+                value_name: Some("Updater".to_string()),
+            }
+        );
+        assert!(info.modified());
+
+        let services = "\\REGISTRY\\MACHINE\\SYSTEM\\CurrentControlSet\\Services";
+        let info = parse_registry_event(REGISTRY_CREATE_KEY_EVENT_ID, 8, &create_key_payload(1, services, "EvilSvc")).unwrap();
+        assert_eq!(info.key_path, format!("{}\\EvilSvc", services));
+        assert!(info.created && info.modified());
+
+        // Opening an existing key or a failed write is not a modification
+        assert!(!parse_registry_event(REGISTRY_CREATE_KEY_EVENT_ID, 8, &create_key_payload(2, "", RUN_KEY)).unwrap().modified());
+        assert!(!parse_registry_event(REGISTRY_SET_VALUE_EVENT_ID, 8, &set_value_payload(0xC000_0022, RUN_KEY, "x")).unwrap().modified());
+
+        assert!(parse_registry_event(REGISTRY_SET_VALUE_EVENT_ID, 8, &[0u8; 12]).is_none());
+        assert!(parse_registry_event(3, 8, &set_value_payload(0, RUN_KEY, "x")).is_none());
+    }
+
+    #[test]
+    fn test_callback_emits_registry_modify_event() {
+        let (event_tx, mut event_rx) = mpsc::channel(4);
+        let context = test_callback_context(event_tx, Arc::new(IocStore::new()));
+
+        for (key, op_status) in [(RUN_KEY, 0), ("\\REGISTRY\\MACHINE\\SOFTWARE\\Contoso\\Settings", 0), (RUN_KEY, 0xC000_0022)] {
+            let mut payload = set_value_payload(op_status, key, "Updater");
+            let mut record = synthetic_record(&mut payload);
+            record.EventHeader.ProviderId = KERNEL_REGISTRY_PROVIDER;
+            record.EventHeader.EventDescriptor.Id = REGISTRY_SET_VALUE_EVENT_ID;
+            record.EventHeader.ProcessId = 3120;
+            record.UserContext = &context as *const CallbackContext as *mut c_void;
+            unsafe { EtwConsumer::event_callback(&mut record) };
+        }
+
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::RegistryModify);
+        assert_eq!(event.mitre_tactic, "TA0003_Persistence");
+        assert_eq!(event.mitre_technique, "T1547.001");
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["pid"], 3120);
+        assert_eq!(payload["key"], RUN_KEY);
+        assert_eq!(payload["value_name"], "Updater");
+        assert_eq!(payload["operation"], "set_value");
+
+        // An ordinary key is reported untagged; the failed write not at all
+        let event = event_rx.try_recv().unwrap();
+        assert_eq!(event.mitre_tactic, "");
+        assert_eq!(event.mitre_technique, "");
+        assert!(event_rx.try_recv().is_err());
+    }
+
+    fn win32_error(code: WIN32_ERROR) -> Error {
+        Error::from(code.to_hresult())
+    }
+
+    #[test]
+    fn test_start_session_restarts_stale_session() {
+        let mut results = vec![
+            Ok(CONTROLTRACE_HANDLE { Value: 7 }),
+            Err(win32_error(ERROR_ALREADY_EXISTS)),
+        ];
+        let mut stopped = false;
+
+        let handle = start_session(|| results.pop().unwrap(), || {
+            stopped = true;
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(stopped);
+        assert_eq!(handle.Value, 7);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_start_session_fails_when_retry_fails() {
+        let mut attempts = 0;
+        let result = start_session(
+            || {
+                attempts += 1;
+                Err(win32_error(ERROR_ALREADY_EXISTS))
+            },
+            || Ok(()),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn test_start_session_other_errors_do_not_retry() {
+        let mut attempts = 0;
+        let mut stopped = false;
+        let result = start_session(
+            || {
+                attempts += 1;
+                Err(win32_error(ERROR_ACCESS_DENIED))
+            },
+            || {
+                stopped = true;
+                Ok(())
+            },
+        );
 
-    loop {
-        std::thread::sleep(std::time::Duration::from_secs(60));
-        debug!("ETW consumer heartbeat");
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+        assert!(!stopped);
     }
 }
@@ -1,12 +0,0 @@
-// Generated protobuf code will be placed here by build.rs
-// This module is auto-generated during the build process
-
-// Re-export the telemetry module when it's generated
-// After running `cargo build`, this will contain:
-// pub mod telemetry;
-
-// For now, provide a placeholder until protobuf compilation is complete
-#[allow(dead_code)]
-pub mod telemetry {
-    // Placeholder - will be replaced by generated code
-}
@@ -0,0 +1,167 @@
+// Component health
+// Collectors and the telemetry client publish their state here so the
+// metrics server can answer `/healthz` for orchestrators.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Process-wide component states. Every update is a single atomic store.
+pub static HEALTH: Health = Health::new();
+
+/// Lifecycle of one component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ComponentState {
+    /// Not used on this host (e.g. ETW on Linux); left out of reports.
+    Inactive = 0,
+    /// Attaching to the kernel source, or connecting to the ingestor.
+    Starting = 1,
+    /// Delivering events (for telemetry: connected to the ingestor).
+    Running = 2,
+    /// Exited cleanly, normally at shutdown.
+    Stopped = 3,
+    /// Gave up after an error (for telemetry: disconnected and retrying).
+    Failed = 4,
+}
+
+impl ComponentState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ComponentState::Inactive => "inactive",
+            ComponentState::Starting => "starting",
+            ComponentState::Running => "running",
+            ComponentState::Stopped => "stopped",
+            ComponentState::Failed => "failed",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ComponentState::Starting,
+            2 => ComponentState::Running,
+            3 => ComponentState::Stopped,
+            4 => ComponentState::Failed,
+            _ => ComponentState::Inactive,
+        }
+    }
+}
+
+/// Event sources that report their state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collector {
+    Etw = 0,
+    Ebpf = 1,
+    AuthLog = 2,
+    Mock = 3,
+}
+
+const COLLECTORS: [Collector; 4] = [Collector::Etw, Collector::Ebpf, Collector::AuthLog, Collector::Mock];
+
+impl Collector {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Collector::Etw => "etw",
+            Collector::Ebpf => "ebpf",
+            Collector::AuthLog => "auth_log",
+            Collector::Mock => "mock",
+        }
+    }
+}
+
+pub struct Health {
+    telemetry: AtomicU8,
+    /// Indexed by `Collector as usize`.
+    collectors: [AtomicU8; 4],
+}
+
+impl Health {
+    /// Every component starts `Inactive`.
+    pub const fn new() -> Self {
+        Self {
+            telemetry: AtomicU8::new(ComponentState::Inactive as u8),
+            collectors: [AtomicU8::new(0), AtomicU8::new(0), AtomicU8::new(0), AtomicU8::new(0)],
+        }
+    }
+
+    pub fn set_telemetry(&self, state: ComponentState) {
+        self.telemetry.store(state as u8, Ordering::Relaxed);
+    }
+
+    pub fn telemetry(&self) -> ComponentState {
+        ComponentState::from_u8(self.telemetry.load(Ordering::Relaxed))
+    }
+
+    pub fn set_collector(&self, collector: Collector, state: ComponentState) {
+        self.collectors[collector as usize].store(state as u8, Ordering::Relaxed);
+    }
+
+    pub fn collector(&self, collector: Collector) -> ComponentState {
+        ComponentState::from_u8(self.collectors[collector as usize].load(Ordering::Relaxed))
+    }
+
+    /// Record how a collector's run ended: `Stopped` on success, `Failed` on error.
+    pub fn collector_finished(&self, collector: Collector, result: &Result<()>) {
+        let state = if result.is_ok() { ComponentState::Stopped } else { ComponentState::Failed };
+        self.set_collector(collector, state);
+    }
+
+    /// Ready when telemetry is connected and at least one collector is running.
+    pub fn is_healthy(&self) -> bool {
+        self.telemetry() == ComponentState::Running
+            && COLLECTORS.iter().any(|&c| self.collector(c) == ComponentState::Running)
+    }
+
+    /// JSON status document served on `/healthz`. Inactive collectors are omitted.
+    pub fn render(&self) -> String {
+        let collectors: serde_json::Map<String, serde_json::Value> = COLLECTORS
+            .iter()
+            .map(|&c| (c, self.collector(c)))
+            .filter(|(_, state)| *state != ComponentState::Inactive)
+            .map(|(c, state)| (c.as_str().to_string(), state.as_str().into()))
+            .collect();
+
+        serde_json::json!({
+            "healthy": self.is_healthy(),
+            "telemetry": self.telemetry().as_str(),
+            "collectors": collectors,
+        })
+        .to_string()
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_requires_telemetry_and_a_running_collector() {
+        let health = Health::new();
+        assert!(!health.is_healthy());
+        assert_eq!(health.render(), r#"{"collectors":{},"healthy":false,"telemetry":"inactive"}"#);
+
+        health.set_telemetry(ComponentState::Running);
+        health.set_collector(Collector::Ebpf, ComponentState::Starting);
+        assert!(!health.is_healthy());
+
+        health.set_collector(Collector::AuthLog, ComponentState::Running);
+        assert!(health.is_healthy());
+
+        health.collector_finished(Collector::AuthLog, &Err(anyhow::anyhow!("gone")));
+        assert_eq!(health.collector(Collector::AuthLog), ComponentState::Failed);
+        assert!(!health.is_healthy());
+
+        health.set_collector(Collector::Ebpf, ComponentState::Running);
+        health.set_telemetry(ComponentState::Failed);
+        assert!(!health.is_healthy());
+        assert_eq!(
+            health.render(),
+            r#"{"collectors":{"auth_log":"failed","ebpf":"running"},"healthy":false,"telemetry":"failed"}"#
+        );
+    }
+}
@@ -0,0 +1,329 @@
+// Local threat-intelligence (IOC) matching
+// Holds known-bad IP addresses, CIDR blocks and domains loaded from a JSON
+// feed file, and answers lookups from the network collectors.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::info;
+
+/// Tactic and technique reported for connections to a known-bad address
+/// (Application Layer Protocol C2).
+pub const IOC_TACTIC: &str = "TA0011_Command_and_Control";
+pub const IOC_TECHNIQUE: &str = "T1071";
+
+/// A single indicator. `value` is an IP address, a CIDR block
+/// (`10.0.0.0/8`, `2001:db8::/32`) or a domain name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Indicator {
+    pub id: String,
+    pub value: String,
+}
+
+/// On-disk feed format for `IocStore::load`.
+#[derive(Deserialize)]
+struct IocFile {
+    indicators: Vec<Indicator>,
+}
+
+/// CIDR blocks of one address family, bucketed by prefix length so a lookup
+/// costs one hash probe per distinct prefix length in the feed.
+#[derive(Debug, Default)]
+struct CidrTable {
+    /// Address width in bits (32 or 128).
+    bits: u8,
+    by_prefix: BTreeMap<u8, HashMap<u128, String>>,
+}
+
+impl CidrTable {
+    fn new(bits: u8) -> Self {
+        Self { bits, by_prefix: BTreeMap::new() }
+    }
+
+    fn insert(&mut self, addr: u128, prefix: u8, id: String) {
+        let network = mask(addr, prefix, self.bits);
+        self.by_prefix.entry(prefix).or_default().entry(network).or_insert(id);
+    }
+
+    /// Most specific block containing `addr`.
+    fn lookup(&self, addr: u128) -> Option<&str> {
+        self.by_prefix
+            .iter()
+            .rev()
+            .find_map(|(&prefix, networks)| networks.get(&mask(addr, prefix, self.bits)))
+            .map(String::as_str)
+    }
+
+    fn len(&self) -> usize {
+        self.by_prefix.values().map(HashMap::len).sum()
+    }
+}
+
+/// Keep the top `prefix` bits of a `bits`-wide address.
+fn mask(addr: u128, prefix: u8, bits: u8) -> u128 {
+    if prefix == 0 {
+        0
+    } else {
+        addr & (u128::MAX << (bits - prefix)) & (u128::MAX >> (128 - bits))
+    }
+}
+
+/// One immutable snapshot of the feed.
+#[derive(Debug)]
+struct IndicatorSet {
+    addresses: HashMap<IpAddr, String>,
+    v4_blocks: CidrTable,
+    v6_blocks: CidrTable,
+    domains: HashMap<String, String>,
+}
+
+impl Default for IndicatorSet {
+    fn default() -> Self {
+        Self {
+            addresses: HashMap::new(),
+            v4_blocks: CidrTable::new(32),
+            v6_blocks: CidrTable::new(128),
+            domains: HashMap::new(),
+        }
+    }
+}
+
+impl IndicatorSet {
+    fn build(indicators: Vec<Indicator>) -> Result<Self> {
+        let mut set = Self::default();
+        for indicator in indicators {
+            set.insert(indicator)?;
+        }
+        Ok(set)
+    }
+
+    fn insert(&mut self, indicator: Indicator) -> Result<()> {
+        let value = indicator.value.trim();
+
+        if let Some((addr, prefix)) = value.split_once('/') {
+            let addr: IpAddr = addr
+                .parse()
+                .with_context(|| format!("Invalid CIDR address in IOC {}: {}", indicator.id, value))?;
+            let prefix: u8 = prefix
+                .parse()
+                .with_context(|| format!("Invalid CIDR prefix in IOC {}: {}", indicator.id, value))?;
+            let (table, bits) = match addr.to_canonical() {
+                IpAddr::V4(v4) => (&mut self.v4_blocks, u128::from(u32::from(v4))),
+                IpAddr::V6(v6) => (&mut self.v6_blocks, u128::from(v6)),
+            };
+            if prefix > table.bits {
+                bail!("CIDR prefix /{} too long in IOC {}: {}", prefix, indicator.id, value);
+            }
+            table.insert(bits, prefix, indicator.id);
+        } else if let Ok(addr) = value.parse::<IpAddr>() {
+            self.addresses.entry(addr.to_canonical()).or_insert(indicator.id);
+        } else {
+            let domain = normalize_domain(value);
+            if domain.is_empty() || domain.contains(|c: char| c.is_whitespace() || c == '/') {
+                bail!("IOC {} is not an IP, CIDR block or domain: {:?}", indicator.id, value);
+            }
+            self.domains.entry(domain).or_insert(indicator.id);
+        }
+
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.addresses.len() + self.v4_blocks.len() + self.v6_blocks.len() + self.domains.len()
+    }
+
+    fn lookup_ip(&self, addr: IpAddr) -> Option<&str> {
+        let addr = addr.to_canonical();
+        if let Some(id) = self.addresses.get(&addr) {
+            return Some(id);
+        }
+        match addr {
+            IpAddr::V4(v4) => self.v4_blocks.lookup(u128::from(u32::from(v4))),
+            IpAddr::V6(v6) => self.v6_blocks.lookup(u128::from(v6)),
+        }
+    }
+
+    fn lookup_domain(&self, domain: &str) -> Option<&str> {
+        let domain = normalize_domain(domain);
+        // Walk up the labels so an indicator also covers its subdomains
+        let mut candidate = domain.as_str();
+        loop {
+            if let Some(id) = self.domains.get(candidate) {
+                return Some(id);
+            }
+            candidate = candidate.split_once('.')?.1;
+        }
+    }
+}
+
+/// Lowercase and drop the root label's trailing dot.
+fn normalize_domain(domain: &str) -> String {
+    domain.trim().trim_end_matches('.').to_lowercase()
+}
+
+/// Thread-safe IOC lookup table that can be reloaded from its feed file
+/// while collectors keep querying it.
+#[derive(Debug, Default)]
+pub struct IocStore {
+    path: Option<PathBuf>,
+    set: RwLock<IndicatorSet>,
+}
+
+impl IocStore {
+    /// Create an empty store that matches nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a store with the given indicators and no backing file.
+    pub fn from_indicators(indicators: Vec<Indicator>) -> Result<Self> {
+        Ok(Self {
+            path: None,
+            set: RwLock::new(IndicatorSet::build(indicators)?),
+        })
+    }
+
+    /// Load a JSON feed of the form `{"indicators": [{"id": ..., "value": ...}]}`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let set = read_feed(path)?;
+        info!("Loaded {} IOC indicators from {}", set.len(), path.display());
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            set: RwLock::new(set),
+        })
+    }
+
+    /// Re-read the feed file and swap it in. On error the current
+    /// indicators stay in effect. Returns the new indicator count.
+    pub fn reload(&self) -> Result<usize> {
+        let Some(path) = &self.path else {
+            return Ok(self.len());
+        };
+
+        let set = read_feed(path)?;
+        let count = set.len();
+        *self.set.write().unwrap_or_else(|e| e.into_inner()) = set;
+        info!("Reloaded {} IOC indicators from {}", count, path.display());
+        Ok(count)
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// IOC id for an exact-address or CIDR hit. The most specific block wins.
+    pub fn lookup_ip(&self, addr: IpAddr) -> Option<String> {
+        self.read().lookup_ip(addr).map(str::to_string)
+    }
+
+    /// IOC id for `domain` or the closest listed parent domain.
+    pub fn lookup_domain(&self, domain: &str) -> Option<String> {
+        self.read().lookup_domain(domain).map(str::to_string)
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, IndicatorSet> {
+        // Writers only swap in a fully built set, so a poisoned lock is still consistent
+        self.set.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn read_feed(path: &Path) -> Result<IndicatorSet> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read IOC file: {}", path.display()))?;
+    let file: IocFile = serde_json::from_str(&contents)
+        .with_context(|| format!("Malformed IOC JSON: {}", path.display()))?;
+    IndicatorSet::build(file.indicators)
+        .with_context(|| format!("Invalid indicator in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indicator(id: &str, value: &str) -> Indicator {
+        Indicator { id: id.to_string(), value: value.to_string() }
+    }
+
+    fn test_store() -> IocStore {
+        IocStore::from_indicators(vec![
+            indicator("IOC-IP", "203.0.113.7"),
+            indicator("IOC-NET", "198.51.100.0/24"),
+            indicator("IOC-WIDE", "198.0.0.0/8"),
+            indicator("IOC-V6", "2001:db8:bad::/48"),
+            indicator("IOC-DOMAIN", "evil.example"),
+        ])
+        .unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_exact_ip_hit() {
+        let store = test_store();
+        assert_eq!(store.lookup_ip(ip("203.0.113.7")), Some("IOC-IP".to_string()));
+        // IPv4-mapped IPv6 is the same host
+        assert_eq!(store.lookup_ip(ip("::ffff:203.0.113.7")), Some("IOC-IP".to_string()));
+    }
+
+    #[test]
+    fn test_cidr_containment_hit() {
+        let store = test_store();
+        assert_eq!(store.lookup_ip(ip("198.51.100.200")), Some("IOC-NET".to_string()));
+        // Falls back to the wider block outside the /24
+        assert_eq!(store.lookup_ip(ip("198.51.101.1")), Some("IOC-WIDE".to_string()));
+        assert_eq!(store.lookup_ip(ip("2001:db8:bad:1::20")), Some("IOC-V6".to_string()));
+    }
+
+    #[test]
+    fn test_clean_miss() {
+        let store = test_store();
+        assert_eq!(store.lookup_ip(ip("203.0.113.8")), None);
+        assert_eq!(store.lookup_ip(ip("10.1.2.3")), None);
+        assert_eq!(store.lookup_ip(ip("2001:db8:bae::1")), None);
+        assert_eq!(store.lookup_domain("example.com"), None);
+        assert_eq!(IocStore::new().lookup_ip(ip("203.0.113.7")), None);
+    }
+
+    #[test]
+    fn test_domain_matches_subdomains() {
+        let store = test_store();
+        assert_eq!(store.lookup_domain("evil.example"), Some("IOC-DOMAIN".to_string()));
+        assert_eq!(store.lookup_domain("CDN.Evil.Example."), Some("IOC-DOMAIN".to_string()));
+        assert_eq!(store.lookup_domain("notevil.example"), None);
+    }
+
+    #[test]
+    fn test_invalid_indicators_rejected() {
+        assert!(IocStore::from_indicators(vec![indicator("BAD", "10.0.0.0/33")]).is_err());
+        assert!(IocStore::from_indicators(vec![indicator("BAD", "10.0.0/8")]).is_err());
+        assert!(IocStore::from_indicators(vec![indicator("BAD", "not a domain")]).is_err());
+    }
+
+    #[test]
+    fn test_load_and_reload() {
+        let feed = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(feed.path(), r#"{"indicators": [{"id": "IOC-1", "value": "192.0.2.1"}]}"#).unwrap();
+
+        let store = IocStore::load(feed.path()).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.lookup_ip(ip("192.0.2.1")), Some("IOC-1".to_string()));
+
+        std::fs::write(feed.path(), r#"{"indicators": [{"id": "IOC-2", "value": "192.0.2.0/24"}]}"#).unwrap();
+        assert_eq!(store.reload().unwrap(), 1);
+        assert_eq!(store.lookup_ip(ip("192.0.2.1")), Some("IOC-2".to_string()));
+
+        // A broken feed keeps the previous indicators
+        std::fs::write(feed.path(), "{").unwrap();
+        assert!(store.reload().is_err());
+        assert_eq!(store.lookup_ip(ip("192.0.2.1")), Some("IOC-2".to_string()));
+    }
+}
@@ -0,0 +1,21 @@
+// Sentinel-Enterprise EDR/DLP Agent library
+// Exposes the agent's components so the binary and integration tests share one build.
+
+pub mod config;
+pub mod logging;
+pub mod proto;
+pub mod dlp;
+pub mod scan;
+pub mod mitre;
+pub mod ioc;
+pub mod telemetry;
+pub mod ratelimit;
+pub mod metrics;
+pub mod health;
+pub mod spool;
+pub mod reload;
+
+pub mod etw;
+pub mod ebpf;
+pub mod authlog;
+pub mod mock;
@@ -0,0 +1,156 @@
+// Log output setup
+// Human-readable text for interactive use, or one JSON object per line for
+// log collectors, tagged with the agent_id.
+
+use std::fmt;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{Format, Json, JsonFields, Writer};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::config::LogFormat;
+
+/// Filter from RUST_LOG, defaulting to the agent's own info-level logs.
+fn env_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| "sentinel_agent=info".into())
+}
+
+/// Text-format subscriber for logging that happens before the configured
+/// format is known (i.e. while the config itself is loading).
+pub fn bootstrap_subscriber() -> impl Subscriber + Send + Sync {
+    tracing_subscriber::registry()
+        .with(env_filter())
+        .with(tracing_subscriber::fmt::layer())
+}
+
+/// Install a text subscriber on stderr for one-shot CLI commands, keeping
+/// stdout free for their machine-readable output.
+pub fn init_cli() {
+    tracing_subscriber::registry()
+        .with(env_filter())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+}
+
+/// Install the global subscriber in the configured format.
+pub fn init(format: LogFormat, agent_id: &str) {
+    let registry = tracing_subscriber::registry().with(env_filter());
+    match format {
+        LogFormat::Text => registry.with(tracing_subscriber::fmt::layer()).init(),
+        LogFormat::Json => registry.with(json_layer(agent_id, std::io::stdout)).init(),
+    }
+}
+
+/// JSON layer writing to `writer`: tracing's standard JSON fields
+/// (timestamp, level, fields, target, spans) plus a constant `agent_id`.
+pub fn json_layer<S, W>(agent_id: &str, writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .fmt_fields(JsonFields::new())
+        .event_format(WithAgentId::new(agent_id))
+        .with_writer(writer)
+}
+
+/// Wraps tracing's JSON formatter and splices `agent_id` into every object;
+/// the stock formatter has no notion of static fields.
+struct WithAgentId {
+    inner: Format<Json>,
+    /// Pre-encoded `"agent_id":"...",` inserted after the opening brace.
+    field: String,
+}
+
+impl WithAgentId {
+    fn new(agent_id: &str) -> Self {
+        Self {
+            inner: tracing_subscriber::fmt::format().json(),
+            field: format!("\"agent_id\":{},", serde_json::Value::from(agent_id)),
+        }
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for WithAgentId
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'w> FormatFields<'w> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let mut line = String::new();
+        self.inner.format_event(ctx, Writer::new(&mut line), event)?;
+
+        match line.strip_prefix('{') {
+            Some(rest) => write!(writer, "{{{}{}", self.field, rest),
+            None => writer.write_str(&line),
+        }
+    }
+}
+
+/// MakeWriter that appends everything to a shared buffer, for tests that
+/// assert on log output.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct Captured(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl Captured {
+    /// Everything written so far.
+    pub(crate) fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+#[cfg(test)]
+impl std::io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<'w> MakeWriter<'w> for Captured {
+    type Writer = Captured;
+
+    fn make_writer(&'w self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_lines_carry_agent_id() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry().with(json_layer("agent-\"1\"", captured.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(batch = 3, "Flushed events");
+            tracing::warn!("Second line");
+        });
+
+        let output = captured.text();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each log line is one JSON object"))
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        let first = &lines[0];
+        assert_eq!(first["agent_id"], "agent-\"1\"");
+        assert_eq!(first["level"], "INFO");
+        assert_eq!(first["fields"]["message"], "Flushed events");
+        assert_eq!(first["fields"]["batch"], 3);
+        assert!(first["timestamp"].is_string());
+        assert!(first["target"].as_str().unwrap().starts_with("sentinel_agent"));
+        assert_eq!(lines[1]["level"], "WARN");
+    }
+}
@@ -3,55 +3,133 @@
 // Supports: Windows (ETW) and Linux (eBPF)
 
 use anyhow::Result;
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::{info, error};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn, error};
 
-mod etw;
-mod dlp;
-mod telemetry;
-mod config;
+use sentinel_agent::config::AgentConfig;
+use sentinel_agent::dlp;
+use sentinel_agent::ioc::IocStore;
+use sentinel_agent::logging;
+use sentinel_agent::metrics;
+use sentinel_agent::ratelimit::RateLimiter;
+use sentinel_agent::reload::{ReloadTrigger, Reloader};
+use sentinel_agent::scan;
+use sentinel_agent::telemetry::{TelemetryClient, Event};
 
-use crate::config::AgentConfig;
-use crate::telemetry::{TelemetryClient, Event};
+#[cfg(target_os = "windows")]
+use sentinel_agent::etw;
+#[cfg(target_os = "linux")]
+use sentinel_agent::{authlog, ebpf};
+#[cfg(feature = "mock")]
+use sentinel_agent::mock;
 
 const EVENT_BUFFER_SIZE: usize = 10000;
-const BATCH_SIZE: usize = 100;
+
+/// Upper bound for each shutdown phase (collectors stopping, telemetry draining).
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Run a one-shot command instead of the agent
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Scan a file or directory against a DLP policy and print matches as JSON
+    Scan {
+        /// DLP policy JSON file to load fingerprints from
+        #[arg(long)]
+        policy: PathBuf,
+
+        /// File or directory to scan (directories are walked recursively)
+        #[arg(long)]
+        path: PathBuf,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    match Cli::parse().command {
+        Some(Command::Scan { policy, path }) => run_scan(&policy, &path),
+        None => run_agent().await,
+    }
+}
+
+/// Offline policy validation: no collectors, no telemetry, no config.
+fn run_scan(policy: &Path, path: &Path) -> Result<()> {
+    logging::init_cli();
+
+    let dlp_engine = dlp::DlpEngine::new();
+    dlp_engine.load_fingerprints_from_policy(&policy.to_string_lossy())?;
+
+    let matches = scan::scan_path(&dlp_engine, path)?;
+    println!("{}", serde_json::to_string_pretty(&matches)?);
+    Ok(())
+}
+
+/// Run the agent until shut down.
+async fn run_agent() -> Result<()> {
+    // Load configuration (agent_id, server endpoint, tenant_id). The log
+    // format comes from it, so anything logged meanwhile goes out as text.
+    let config = tracing::subscriber::with_default(logging::bootstrap_subscriber(), AgentConfig::load)?;
+
     // Initialize lightweight logging (filter by RUST_LOG env var)
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "sentinel_agent=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    logging::init(config.log_format, &config.agent_id);
 
     info!("Sentinel-Enterprise Agent v{} starting...", env!("CARGO_PKG_VERSION"));
-
-    // Load configuration (agent_id, server endpoint, tenant_id)
-    let config = AgentConfig::load()?;
     info!("Agent ID: {}", config.agent_id);
     info!("Ingestor endpoint: {}", config.ingestor_url);
+    info!("Enforcement mode: {}", config.enforcement_mode.as_str());
 
     // Create high-throughput channel for event batching
     let (event_tx, event_rx) = mpsc::channel::<Event>(EVENT_BUFFER_SIZE);
 
     // Initialize DLP engine with fingerprint hashset
     let dlp_engine = Arc::new(dlp::DlpEngine::new());
+    if let Some(policy) = &config.dlp_policy {
+        dlp_engine.load_fingerprints_from_policy(&policy.to_string_lossy())?;
+    }
     info!("DLP engine initialized with {} fingerprints", dlp_engine.fingerprint_count());
 
+    // Threat-intel indicators for network connections
+    let iocs = Arc::new(match &config.ioc_file {
+        Some(path) => IocStore::load(path)?,
+        None => IocStore::new(),
+    });
+
     // Start telemetry client (gRPC stream to ingestor)
     let telemetry_client = TelemetryClient::new(config.clone()).await?;
-    let telemetry_handle = tokio::spawn(async move {
+    let mut telemetry_handle = tokio::spawn(async move {
         if let Err(e) = telemetry_client.run(event_rx).await {
             error!("Telemetry client error: {}", e);
         }
     });
 
+    // One set of buckets for the whole agent, so limits hold across collectors
+    let rate_limiter = Arc::new(RateLimiter::new(&config.rate_limits));
+
+    // Cancelled on SIGTERM/Ctrl-C to stop every collector
+    let shutdown = CancellationToken::new();
+    let mut collector_handles = Vec::new();
+
+    if config.metrics_port != 0 {
+        let metrics_shutdown = shutdown.clone();
+        let port = config.metrics_port;
+        tokio::spawn(async move {
+            if let Err(e) = metrics::start_server(port, metrics_shutdown).await {
+                error!("Metrics endpoint error: {:#}", e);
+            }
+        });
+    }
+
     // Platform-specific event collection
     #[cfg(target_os = "windows")]
     {
@@ -59,12 +137,15 @@ async fn main() -> Result<()> {
         let etw_tx = event_tx.clone();
         let etw_config = config.clone();
         let dlp_ref = dlp_engine.clone();
+        let ioc_ref = iocs.clone();
+        let limiter_ref = rate_limiter.clone();
+        let etw_shutdown = shutdown.clone();
 
-        tokio::task::spawn_blocking(move || {
-            if let Err(e) = etw::start_consumer(etw_tx, etw_config, dlp_ref) {
+        collector_handles.push(tokio::task::spawn_blocking(move || {
+            if let Err(e) = etw::start_consumer(etw_tx, etw_config, dlp_ref, ioc_ref, limiter_ref, etw_shutdown) {
                 error!("ETW consumer error: {}", e);
             }
-        });
+        }));
     }
 
     #[cfg(target_os = "linux")]
@@ -73,18 +154,104 @@ async fn main() -> Result<()> {
         let ebpf_tx = event_tx.clone();
         let ebpf_config = config.clone();
         let dlp_ref = dlp_engine.clone();
+        let ioc_ref = iocs.clone();
+        let limiter_ref = rate_limiter.clone();
+        let ebpf_shutdown = shutdown.clone();
 
-        tokio::spawn(async move {
-            if let Err(e) = ebpf::start_collectors(ebpf_tx, ebpf_config, dlp_ref).await {
+        collector_handles.push(tokio::spawn(async move {
+            if let Err(e) = ebpf::start_collectors(ebpf_tx, ebpf_config, dlp_ref, ioc_ref, limiter_ref, ebpf_shutdown).await {
                 error!("eBPF collector error: {}", e);
             }
-        });
+        }));
+
+        let auth_tx = event_tx.clone();
+        let auth_config = config.clone();
+        let auth_limiter = rate_limiter.clone();
+        let auth_shutdown = shutdown.clone();
+        collector_handles.push(tokio::spawn(async move {
+            if let Err(e) = authlog::start_auth_collector(auth_tx, auth_config, auth_limiter, auth_shutdown).await {
+                error!("Auth log collector error: {:#}", e);
+            }
+        }));
+    }
+
+    #[cfg(feature = "mock")]
+    {
+        info!("Starting mock collector...");
+        let mock_tx = event_tx.clone();
+        let mock_config = config.clone();
+        let dlp_ref = dlp_engine.clone();
+        let limiter_ref = rate_limiter.clone();
+        let mock_shutdown = shutdown.clone();
+        collector_handles.push(tokio::spawn(async move {
+            if let Err(e) = mock::start_mock_collector(mock_tx, mock_config, dlp_ref, limiter_ref, mock_shutdown).await {
+                error!("Mock collector error: {:#}", e);
+            }
+        }));
+    }
+
+    // SIGHUP / the reload event swaps in a new policy and IOC feed under the
+    // running collectors
+    match ReloadTrigger::install() {
+        Ok(trigger) => {
+            let reloader = Reloader::new(config.clone(), AgentConfig::load, dlp_engine.clone(), iocs.clone());
+            tokio::spawn(reloader.run(trigger, shutdown.clone()));
+        }
+        Err(e) => warn!("Hot reload unavailable: {:#}", e),
     }
 
     info!("Agent fully operational. Monitoring system events...");
 
-    // Wait for telemetry client (runs indefinitely)
-    telemetry_handle.await?;
+    // Run until a shutdown signal arrives (or the telemetry client dies)
+    tokio::select! {
+        _ = shutdown_signal() => info!("Shutdown signal received, stopping collectors..."),
+        _ = &mut telemetry_handle => {
+            warn!("Telemetry client exited unexpectedly, shutting down");
+            shutdown.cancel();
+            return Ok(());
+        }
+    }
+
+    shutdown.cancel();
+    for handle in collector_handles {
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, handle).await.is_err() {
+            warn!("Collector did not stop within {:?}", SHUTDOWN_TIMEOUT);
+        }
+    }
+
+    // Once the last sender is gone the telemetry client flushes and exits
+    drop(event_tx);
+    info!("Draining remaining events to ingestor...");
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, telemetry_handle).await.is_err() {
+        warn!("Timed out draining events; some telemetry may be lost");
+    }
 
+    info!("Agent stopped");
     Ok(())
 }
+
+/// Resolve when the process is asked to stop (SIGTERM/SIGINT on Unix, Ctrl-C elsewhere).
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+            }
+            Err(e) => {
+                warn!("Failed to install SIGTERM handler: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
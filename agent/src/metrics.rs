@@ -0,0 +1,302 @@
+// Agent operational metrics
+// Lock-free counters bumped on the hot paths, served in the Prometheus text
+// exposition format from a minimal HTTP endpoint that also answers health checks.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+use crate::dlp::Severity;
+use crate::health::{Health, HEALTH};
+
+/// Largest request head accepted from a scraper.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+const SEVERITIES: [(Severity, &str); 4] = [
+    (Severity::Low, "low"),
+    (Severity::Medium, "medium"),
+    (Severity::High, "high"),
+    (Severity::Critical, "critical"),
+];
+
+/// Process-wide counters. Every update is a single relaxed atomic add.
+pub static METRICS: Metrics = Metrics::new();
+
+pub struct Metrics {
+    events_produced: AtomicU64,
+    events_sent: AtomicU64,
+    events_dropped: AtomicU64,
+    events_rate_limited: AtomicU64,
+    events_filtered: AtomicU64,
+    spool_evicted: AtomicU64,
+    dlp_buffers_skipped: AtomicU64,
+    /// Indexed by `Severity as usize - 1`.
+    dlp_matches: [AtomicU64; 4],
+}
+
+impl Metrics {
+    /// All counters start at zero.
+    pub const fn new() -> Self {
+        Self {
+            events_produced: AtomicU64::new(0),
+            events_sent: AtomicU64::new(0),
+            events_dropped: AtomicU64::new(0),
+            events_rate_limited: AtomicU64::new(0),
+            events_filtered: AtomicU64::new(0),
+            spool_evicted: AtomicU64::new(0),
+            dlp_buffers_skipped: AtomicU64::new(0),
+            dlp_matches: [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)],
+        }
+    }
+
+    /// A collector created an event.
+    pub fn event_produced(&self) {
+        self.events_produced.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Events handed to the ingestor stream.
+    pub fn events_sent(&self, count: u64) {
+        self.events_sent.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// An event was discarded (full channel or full telemetry buffer).
+    pub fn event_dropped(&self) {
+        self.events_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A collector's rate limit sampled out an event.
+    pub fn event_rate_limited(&self) {
+        self.events_rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An event fell below `min_severity` and was not sent.
+    pub fn event_filtered(&self) {
+        self.events_filtered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The offline spool discarded its oldest events to stay under its cap.
+    pub fn spool_evicted(&self, count: u64) {
+        self.spool_evicted.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// The DLP engine skipped a buffer shorter than its minimum scan size.
+    pub fn dlp_buffer_skipped(&self) {
+        self.dlp_buffers_skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The DLP engine matched a fingerprint.
+    pub fn dlp_match(&self, severity: Severity) {
+        self.dlp_matches[severity as usize - 1].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render all counters in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let counters = [
+            ("sentinel_events_produced_total", "Events created by collectors.", &self.events_produced),
+            ("sentinel_events_sent_total", "Events streamed to the ingestor.", &self.events_sent),
+            ("sentinel_events_dropped_total", "Events discarded because a queue was full.", &self.events_dropped),
+            ("sentinel_events_rate_limited_total", "Events sampled out by per-type rate limits.", &self.events_rate_limited),
+            ("sentinel_events_filtered_total", "Events discarded for falling below min_severity.", &self.events_filtered),
+            ("sentinel_spool_evicted_total", "Spooled events evicted to respect spool_max_bytes.", &self.spool_evicted),
+            ("sentinel_dlp_buffers_skipped_total", "Buffers not DLP-scanned because they were below the minimum scan size.", &self.dlp_buffers_skipped),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, counter.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(out, "# HELP sentinel_dlp_matches_total DLP fingerprint matches by severity.");
+        let _ = writeln!(out, "# TYPE sentinel_dlp_matches_total counter");
+        for (severity, label) in SEVERITIES {
+            let _ = writeln!(
+                out,
+                "sentinel_dlp_matches_total{{severity=\"{}\"}} {}",
+                label,
+                self.dlp_matches[severity as usize - 1].load(Ordering::Relaxed)
+            );
+        }
+
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serve `/metrics` and `/healthz` on all interfaces at `port` until
+/// `shutdown` is cancelled.
+pub async fn start_server(port: u16, shutdown: CancellationToken) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to bind metrics endpoint on port {}", port))?;
+    info!("Metrics endpoint listening on {}", listener.local_addr()?);
+
+    serve(listener, &HEALTH, shutdown).await
+}
+
+/// Accept scrapes on an already-bound listener until `shutdown` is cancelled.
+/// `/healthz` reports the component states published to `health`.
+pub async fn serve(listener: TcpListener, health: &'static Health, shutdown: CancellationToken) -> Result<()> {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            accepted = listener.accept() => {
+                let (stream, peer) = accepted.context("Metrics endpoint accept failed")?;
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, health).await {
+                        debug!("Metrics request from {} failed: {}", peer, e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Answer a single HTTP/1.x request and close the connection.
+async fn handle_connection(mut stream: TcpStream, health: &Health) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || request.len() + n > MAX_REQUEST_SIZE {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            ("200 OK", "text/plain; version=0.0.4", METRICS.render())
+        }
+        (Some("GET"), Some("/healthz")) => {
+            let status = if health.is_healthy() { "200 OK" } else { "503 Service Unavailable" };
+            (status, "application/json", health.render())
+        }
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_render_exposition_format() {
+        let metrics = Metrics::new();
+        metrics.event_produced();
+        metrics.event_produced();
+        metrics.events_sent(2);
+        metrics.event_dropped();
+        metrics.event_rate_limited();
+        metrics.event_filtered();
+        metrics.spool_evicted(3);
+        metrics.dlp_buffer_skipped();
+        metrics.dlp_match(Severity::Critical);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE sentinel_events_produced_total counter\n"));
+        assert!(text.contains("sentinel_events_produced_total 2\n"));
+        assert!(text.contains("sentinel_events_sent_total 2\n"));
+        assert!(text.contains("sentinel_events_dropped_total 1\n"));
+        assert!(text.contains("sentinel_events_rate_limited_total 1\n"));
+        assert!(text.contains("sentinel_events_filtered_total 1\n"));
+        assert!(text.contains("sentinel_spool_evicted_total 3\n"));
+        assert!(text.contains("sentinel_dlp_buffers_skipped_total 1\n"));
+        assert!(text.contains("sentinel_dlp_matches_total{severity=\"critical\"} 1\n"));
+        assert!(text.contains("sentinel_dlp_matches_total{severity=\"low\"} 0\n"));
+    }
+
+    #[tokio::test]
+    async fn test_scrape_metrics_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn(serve(listener, &HEALTH, shutdown.clone()));
+
+        METRICS.event_produced();
+        METRICS.dlp_match(Severity::High);
+
+        let response = get(addr, "/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+        for name in [
+            "sentinel_events_produced_total",
+            "sentinel_events_sent_total",
+            "sentinel_events_dropped_total",
+            "sentinel_dlp_matches_total{severity=\"high\"}",
+        ] {
+            assert!(response.contains(name), "missing {} in:\n{}", name, response);
+        }
+
+        let response = get(addr, "/other").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        shutdown.cancel();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_healthz_follows_component_state() {
+        use crate::health::{Collector, ComponentState};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        // A private instance: other tests drive the real telemetry client, which updates HEALTH
+        let health: &'static Health = Box::leak(Box::new(Health::new()));
+        let server = tokio::spawn(serve(listener, health, shutdown.clone()));
+
+        health.set_collector(Collector::Ebpf, ComponentState::Running);
+        health.set_telemetry(ComponentState::Starting);
+        let response = get(addr, "/healthz").await;
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.contains("Content-Type: application/json"));
+        assert!(response.ends_with(r#"{"collectors":{"ebpf":"running"},"healthy":false,"telemetry":"starting"}"#));
+
+        health.set_telemetry(ComponentState::Running);
+        let response = get(addr, "/healthz").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"collectors":{"ebpf":"running"},"healthy":true,"telemetry":"running"}"#));
+
+        health.set_collector(Collector::Ebpf, ComponentState::Failed);
+        let response = get(addr, "/healthz").await;
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.contains(r#""ebpf":"failed""#));
+
+        shutdown.cancel();
+        server.await.unwrap().unwrap();
+    }
+}
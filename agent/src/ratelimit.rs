@@ -0,0 +1,206 @@
+// Per-event-type rate limiting
+// Token buckets that sample high-volume, low-value event types (file and
+// network activity on busy servers) before they reach the telemetry channel.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::dlp::Severity;
+use crate::telemetry::{Event, EventType};
+
+/// Configured limits in events per second, keyed by event type.
+///
+/// Written as a table of snake_case event type names, e.g. in TOML
+/// `[rate_limits]` / `file_access = 100`, or as
+/// `SENTINEL_RATE_LIMITS=file_access=100,network_conn=500`.
+/// Types not listed are unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BTreeMap<String, u32>", into = "BTreeMap<String, u32>")]
+pub struct RateLimits(BTreeMap<EventType, u32>);
+
+impl RateLimits {
+    pub fn new(limits: BTreeMap<EventType, u32>) -> Result<Self> {
+        for (&event_type, &rate) in &limits {
+            if event_type == EventType::DlpViolation {
+                bail!("dlp_violation events are never rate limited");
+            }
+            if rate == 0 {
+                bail!("Rate limit for {} must be non-zero (omit it to leave the type unlimited)", event_type.as_str());
+            }
+        }
+        Ok(Self(limits))
+    }
+
+    pub fn get(&self, event_type: EventType) -> Option<u32> {
+        self.0.get(&event_type).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl TryFrom<BTreeMap<String, u32>> for RateLimits {
+    type Error = anyhow::Error;
+
+    fn try_from(table: BTreeMap<String, u32>) -> Result<Self> {
+        let limits = table
+            .into_iter()
+            .map(|(name, rate)| Ok((name.parse()?, rate)))
+            .collect::<Result<_>>()?;
+        Self::new(limits)
+    }
+}
+
+impl From<RateLimits> for BTreeMap<String, u32> {
+    fn from(limits: RateLimits) -> Self {
+        limits.0.into_iter().map(|(event_type, rate)| (event_type.as_str().to_string(), rate)).collect()
+    }
+}
+
+impl FromStr for RateLimits {
+    type Err = anyhow::Error;
+
+    /// Parse `type=rate` pairs separated by commas.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut table = BTreeMap::new();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let Some((name, rate)) = pair.split_once('=') else {
+                bail!("Expected event_type=rate, got {:?}", pair);
+            };
+            let rate = rate.trim().parse().map_err(|e| anyhow::anyhow!("Invalid rate in {:?}: {}", pair, e))?;
+            table.insert(name.trim().to_string(), rate);
+        }
+        Self::try_from(table)
+    }
+}
+
+/// Classic token bucket: holds up to one second of `rate` tokens and
+/// refills continuously.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32, now: Instant) -> Self {
+        // Start full so a quiet agent can report an initial burst
+        Self { rate: rate as f64, tokens: rate as f64, last_refill: now }
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Decides which events may enter the telemetry channel.
+/// DLP violations and Critical events always pass.
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: BTreeMap<EventType, Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: &RateLimits) -> Self {
+        let now = Instant::now();
+        Self {
+            buckets: limits.0.iter().map(|(&event_type, &rate)| (event_type, Mutex::new(TokenBucket::new(rate, now)))).collect(),
+        }
+    }
+
+    /// Whether `event` may be sent now.
+    pub fn allow(&self, event: &Event) -> bool {
+        self.allow_at(event, Instant::now())
+    }
+
+    fn allow_at(&self, event: &Event, now: Instant) -> bool {
+        if event.event_type == EventType::DlpViolation || event.severity >= i32::from(Severity::Critical) {
+            return true;
+        }
+
+        match self.buckets.get(&event.event_type) {
+            Some(bucket) => bucket.lock().unwrap_or_else(|e| e.into_inner()).try_take(now),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::EventContext;
+    use std::time::Duration;
+
+    fn event(event_type: EventType, severity: i32) -> Event {
+        let context = EventContext::new("agent-1".to_string(), "tenant-1".to_string());
+        let mut event = Event::new(&context, event_type, "TA0009_Collection".to_string(), String::new());
+        event.severity = severity;
+        event
+    }
+
+    fn limiter(spec: &str) -> RateLimiter {
+        RateLimiter::new(&spec.parse().unwrap())
+    }
+
+    #[test]
+    fn test_token_bucket_samples_to_rate() {
+        let limiter = limiter("file_access=100");
+        let start = Instant::now();
+        let file_access = event(EventType::FileAccess, 1);
+
+        // A burst spends the one-second allowance...
+        let burst = (0..1000).filter(|_| limiter.allow_at(&file_access, start)).count();
+        assert_eq!(burst, 100);
+
+        // ...then 1000 events spread over the next simulated second get ~100 through
+        let passed = (1..=1000)
+            .filter(|&ms| limiter.allow_at(&file_access, start + Duration::from_millis(ms)))
+            .count();
+        assert!((99..=101).contains(&passed), "{} events passed", passed);
+    }
+
+    #[test]
+    fn test_unlisted_and_exempt_events_pass() {
+        let limiter = limiter("file_access=1,network_conn=1");
+        let now = Instant::now();
+
+        for _ in 0..50 {
+            assert!(limiter.allow_at(&event(EventType::ProcessStart, 1), now));
+            assert!(limiter.allow_at(&event(EventType::DlpViolation, 2), now));
+            // Critical severity overrides the type's limit
+            assert!(limiter.allow_at(&event(EventType::NetworkConn, 4), now));
+        }
+        assert!(limiter.allow_at(&event(EventType::NetworkConn, 3), now));
+        assert!(!limiter.allow_at(&event(EventType::NetworkConn, 3), now));
+    }
+
+    #[test]
+    fn test_parse_rate_limits() {
+        let limits: RateLimits = " file_access=100, NetworkConn = 5 ".parse().unwrap();
+        assert_eq!(limits.get(EventType::FileAccess), Some(100));
+        assert_eq!(limits.get(EventType::NetworkConn), Some(5));
+        assert_eq!(limits.get(EventType::ProcessStart), None);
+        assert!("".parse::<RateLimits>().unwrap().is_empty());
+
+        assert!("file_access".parse::<RateLimits>().is_err());
+        assert!("file_access=0".parse::<RateLimits>().is_err());
+        assert!("file_access=-1".parse::<RateLimits>().is_err());
+        assert!("bogus=10".parse::<RateLimits>().is_err());
+        assert!("dlp_violation=10".parse::<RateLimits>().is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// Hot reload
+// On SIGHUP (Unix) or a named event (Windows) the config file, DLP policy
+// and IOC feed are re-read and swapped in without restarting collectors.
+// Settings baked into running components are reported as needing a restart.
+
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::config::AgentConfig;
+use crate::dlp::DlpEngine;
+use crate::ioc::IocStore;
+
+/// Config fields `Reloader::reload` applies; a change to any other one
+/// only takes effect after a restart.
+const HOT_RELOADABLE: &[&str] = &["dlp_policy"];
+
+/// Windows event that triggers a reload when signalled (`SetEvent`).
+#[cfg(windows)]
+pub const RELOAD_EVENT_NAME: &str = "Global\\SentinelAgentReload";
+
+/// Source of reload requests: SIGHUP on Unix, `RELOAD_EVENT_NAME` on Windows.
+pub struct ReloadTrigger {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+    #[cfg(windows)]
+    event: windows::Win32::Foundation::HANDLE,
+}
+
+impl ReloadTrigger {
+    /// Start listening. Requests made before this call are not seen, and on
+    /// Unix SIGHUP no longer terminates the process once it returns.
+    pub fn install() -> Result<Self> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let hangup = signal(SignalKind::hangup()).context("Failed to install SIGHUP handler")?;
+            Ok(Self { hangup })
+        }
+
+        #[cfg(windows)]
+        {
+            use windows::core::HSTRING;
+            use windows::Win32::System::Threading::CreateEventW;
+
+            // Auto-reset, so each SetEvent is one reload
+            let event = unsafe { CreateEventW(None, false, false, &HSTRING::from(RELOAD_EVENT_NAME)) }
+                .with_context(|| format!("Failed to create reload event {}", RELOAD_EVENT_NAME))?;
+            Ok(Self { event })
+        }
+    }
+
+    /// Wait for the next reload request.
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        {
+            if self.hangup.recv().await.is_none() {
+                std::future::pending::<()>().await;
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            use windows::Win32::Foundation::WAIT_OBJECT_0;
+            use windows::Win32::System::Threading::WaitForSingleObject;
+
+            // Poll rather than block a thread that shutdown could not interrupt
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                if unsafe { WaitForSingleObject(self.event, 0) } == WAIT_OBJECT_0 {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ReloadTrigger {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = windows::Win32::Foundation::CloseHandle(self.event);
+        }
+    }
+}
+
+/// Applies reload requests to the live DLP engine and IOC store.
+pub struct Reloader {
+    /// Settings currently in effect.
+    config: AgentConfig,
+    load_config: Box<dyn Fn() -> Result<AgentConfig> + Send>,
+    dlp_engine: Arc<DlpEngine>,
+    iocs: Arc<IocStore>,
+}
+
+impl Reloader {
+    /// `load_config` re-reads configuration the same way startup did
+    /// (normally `AgentConfig::load`); `config` is what is running now.
+    pub fn new<F>(config: AgentConfig, load_config: F, dlp_engine: Arc<DlpEngine>, iocs: Arc<IocStore>) -> Self
+    where
+        F: Fn() -> Result<AgentConfig> + Send + 'static,
+    {
+        Self {
+            config,
+            load_config: Box::new(load_config),
+            dlp_engine,
+            iocs,
+        }
+    }
+
+    /// Re-read the configuration, DLP policy and IOC feed and apply what can
+    /// change at runtime. Whatever fails keeps its current data; the DLP
+    /// policy and IOC feed are reloaded independently of each other.
+    pub fn reload(&mut self) -> Result<()> {
+        let new = (self.load_config)().context("Invalid configuration; keeping the running one")?;
+
+        for field in restart_required(&self.config, &new) {
+            warn!("Config change to {} requires an agent restart to take effect", field);
+        }
+
+        let policy = self.reload_policy(&new);
+        let iocs = self.iocs.reload().map(|_| ()).context("Failed to reload IOC feed");
+        policy.and(iocs)
+    }
+
+    fn reload_policy(&mut self, new: &AgentConfig) -> Result<()> {
+        let Some(path) = &new.dlp_policy else {
+            if self.config.dlp_policy.is_some() {
+                warn!("DLP policy removed from config; its fingerprints stay loaded until restart");
+            }
+            return Ok(());
+        };
+
+        let count = self.dlp_engine.reload_policy(&path.to_string_lossy())
+            .with_context(|| format!("Failed to reload DLP policy {}", path.display()))?;
+        info!("Reloaded {} DLP fingerprints from {}", count, path.display());
+        self.config.dlp_policy = new.dlp_policy.clone();
+        Ok(())
+    }
+
+    /// Reload on every request from `trigger` until `shutdown` is cancelled.
+    pub async fn run(mut self, mut trigger: ReloadTrigger, shutdown: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = trigger.recv() => {
+                    info!("Reload requested");
+                    if let Err(e) = self.reload() {
+                        error!("Reload incomplete: {:#}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Settings that differ between `running` and `new` but cannot be applied live.
+fn restart_required(running: &AgentConfig, new: &AgentConfig) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(running)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(running), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+
+    new.into_iter()
+        .filter(|(field, value)| !HOT_RELOADABLE.contains(&field.as_str()) && running.get(field) != Some(value))
+        .map(|(field, _)| field)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_restart_required_lists_changed_fields() {
+        let running = AgentConfig::default();
+        let mut new = AgentConfig {
+            agent_id: running.agent_id.clone(),
+            dlp_policy: Some(PathBuf::from("dlp.json")),
+            ..AgentConfig::default()
+        };
+        assert!(restart_required(&running, &new).is_empty());
+
+        new.ingestor_url = "https://ingest.example.com:443".to_string();
+        new.batch_size = 10;
+        assert_eq!(restart_required(&running, &new), vec!["batch_size", "ingestor_url"]);
+    }
+
+    #[test]
+    fn test_failed_config_load_changes_nothing() {
+        let engine = Arc::new(DlpEngine::new());
+        engine.add_fingerprint("abc", "KEEP".to_string(), crate::dlp::Severity::Low);
+        let mut reloader = Reloader::new(
+            AgentConfig::default(),
+            || Err(anyhow::anyhow!("bad file")),
+            engine.clone(),
+            Arc::new(IocStore::new()),
+        );
+
+        assert!(reloader.reload().is_err());
+        assert_eq!(engine.fingerprint_count(), 1);
+    }
+}
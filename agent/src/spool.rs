@@ -0,0 +1,309 @@
+// Offline event spool
+// Bounded on-disk FIFO that holds events while the ingestor is unreachable
+// and replays them, oldest first, once the connection returns.
+
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::metrics::METRICS;
+use crate::telemetry::Event;
+
+/// Number of segments the byte cap is split into. Eviction drops a whole
+/// segment, so this bounds how much history one eviction discards.
+const SEGMENTS_PER_SPOOL: u64 = 8;
+
+/// Lower bound on segment size so tiny caps don't create a file per event.
+const MIN_SEGMENT_BYTES: u64 = 4096;
+
+const SEGMENT_EXTENSION: &str = "jsonl";
+
+/// One spool file: newline-delimited JSON events, appended in order.
+#[derive(Debug)]
+struct Segment {
+    seq: u64,
+    path: PathBuf,
+    bytes: u64,
+    events: u64,
+}
+
+/// Disk-backed ring buffer of events, capped at `max_bytes`.
+///
+/// Events are appended to numbered segment files. When the cap is exceeded
+/// the oldest segment is deleted and its events counted as evicted. Segments
+/// left over from a previous run are picked up by `open`.
+#[derive(Debug)]
+pub struct DiskSpool {
+    dir: PathBuf,
+    max_bytes: u64,
+    segment_bytes: u64,
+    segments: VecDeque<Segment>,
+    total_bytes: u64,
+    evicted: u64,
+    /// The newest segment is being replayed; the next push starts another.
+    sealed: bool,
+}
+
+impl DiskSpool {
+    /// Open (creating if needed) the spool in `dir`.
+    pub fn open(dir: &Path, max_bytes: u64) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create spool directory {}", dir.display()))?;
+
+        let mut segments = Vec::new();
+        let entries = fs::read_dir(dir)
+            .with_context(|| format!("Failed to list spool directory {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            let Some(seq) = path.file_stem().and_then(|stem| stem.to_str()?.parse::<u64>().ok()) else {
+                continue;
+            };
+
+            let contents = fs::read(&path)
+                .with_context(|| format!("Failed to read spool segment {}", path.display()))?;
+            segments.push(Segment {
+                seq,
+                path,
+                bytes: contents.len() as u64,
+                events: contents.iter().filter(|&&b| b == b'\n').count() as u64,
+            });
+        }
+        segments.sort_by_key(|segment| segment.seq);
+
+        let spool = Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            segment_bytes: (max_bytes / SEGMENTS_PER_SPOOL).max(MIN_SEGMENT_BYTES),
+            total_bytes: segments.iter().map(|segment| segment.bytes).sum(),
+            segments: segments.into(),
+            evicted: 0,
+            sealed: false,
+        };
+
+        if !spool.is_empty() {
+            info!("Resuming offline spool with {} event(s) in {}", spool.len(), dir.display());
+        }
+        Ok(spool)
+    }
+
+    /// Number of events currently spooled.
+    pub fn len(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.events).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Bytes currently used on disk.
+    pub fn bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Events discarded to stay under `max_bytes` since the spool was opened.
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+
+    /// Append events after everything already spooled, evicting the oldest
+    /// segments if the cap is exceeded.
+    pub fn push(&mut self, events: &[Event]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut encoded = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut encoded, event).context("Failed to encode spooled event")?;
+            encoded.push(b'\n');
+        }
+
+        let needs_new_segment = self.sealed
+            || self
+                .segments
+                .back()
+                .is_none_or(|segment| segment.bytes >= self.segment_bytes);
+        if needs_new_segment {
+            let seq = self.segments.back().map_or(0, |segment| segment.seq + 1);
+            let path = self.dir.join(format!("{:020}.{}", seq, SEGMENT_EXTENSION));
+            self.segments.push_back(Segment { seq, path, bytes: 0, events: 0 });
+            self.sealed = false;
+        }
+
+        let segment = self.segments.back_mut().expect("segment was just ensured");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&segment.path)
+            .with_context(|| format!("Failed to open spool segment {}", segment.path.display()))?;
+        file.write_all(&encoded)
+            .with_context(|| format!("Failed to write spool segment {}", segment.path.display()))?;
+
+        segment.bytes += encoded.len() as u64;
+        segment.events += events.len() as u64;
+        self.total_bytes += encoded.len() as u64;
+
+        // Never evict the segment just written; it holds the newest events
+        while self.total_bytes > self.max_bytes && self.segments.len() > 1 {
+            let oldest = self.segments.pop_front().expect("more than one segment");
+            self.remove_segment(&oldest);
+            self.evicted += oldest.events;
+            METRICS.spool_evicted(oldest.events);
+            warn!(
+                "Offline spool over {} bytes, evicted {} oldest event(s) ({} so far)",
+                self.max_bytes, oldest.events, self.evicted
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Read the oldest segment's events without removing them, along with
+    /// the segment id to pass to `remove` once they have been delivered.
+    /// Later pushes never append to the returned segment.
+    pub fn peek_oldest(&mut self) -> Result<Option<(u64, Vec<Event>)>> {
+        let Some(segment) = self.segments.front() else {
+            return Ok(None);
+        };
+        if self.segments.len() == 1 {
+            self.sealed = true;
+        }
+
+        let contents = fs::read_to_string(&segment.path)
+            .with_context(|| format!("Failed to read spool segment {}", segment.path.display()))?;
+        let mut events = Vec::new();
+        for line in contents.lines().filter(|line| !line.is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(event) => events.push(event),
+                Err(e) => warn!("Skipping corrupt spooled event in {}: {}", segment.path.display(), e),
+            }
+        }
+
+        Ok(Some((segment.seq, events)))
+    }
+
+    /// Delete segment `id` after its events were delivered. A segment
+    /// evicted in the meantime is already gone.
+    pub fn remove(&mut self, id: u64) {
+        if let Some(index) = self.segments.iter().position(|segment| segment.seq == id) {
+            let segment = self.segments.remove(index).expect("index was just found");
+            self.remove_segment(&segment);
+        }
+    }
+
+    /// Delete the oldest segment without reading it, e.g. when it is unreadable.
+    pub fn pop_oldest(&mut self) {
+        if let Some(segment) = self.segments.pop_front() {
+            self.remove_segment(&segment);
+        }
+    }
+
+    fn remove_segment(&mut self, segment: &Segment) {
+        self.total_bytes -= segment.bytes;
+        if let Err(e) = fs::remove_file(&segment.path) {
+            warn!("Failed to remove spool segment {}: {}", segment.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::{EventContext, EventType, MockClock};
+
+    fn spool_event(n: i64) -> Event {
+        let context = EventContext::new("agent-1".to_string(), "tenant-1".to_string());
+        Event::new_with_clock(
+            &context,
+            EventType::ProcessStart,
+            "TA0002_Execution".to_string(),
+            format!(r#"{{"seq":{}}}"#, n),
+            &MockClock::new(n),
+        )
+    }
+
+    fn drain(spool: &mut DiskSpool) -> Vec<i64> {
+        let mut seen = Vec::new();
+        while let Some((id, events)) = spool.peek_oldest().unwrap() {
+            seen.extend(events.iter().map(|event| event.timestamp));
+            spool.remove(id);
+        }
+        seen
+    }
+
+    #[test]
+    fn test_push_and_drain_preserves_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = DiskSpool::open(dir.path(), 1024 * 1024).unwrap();
+
+        for n in 0..10 {
+            spool.push(&[spool_event(n * 2), spool_event(n * 2 + 1)]).unwrap();
+        }
+        assert_eq!(spool.len(), 20);
+        assert!(spool.bytes() > 0);
+
+        assert_eq!(drain(&mut spool), (0..20).collect::<Vec<_>>());
+        assert!(spool.is_empty());
+        assert_eq!(spool.bytes(), 0);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_cap_evicts_oldest_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = DiskSpool::open(dir.path(), 16 * 1024).unwrap();
+        let batch: Vec<Event> = (0..10).map(spool_event).collect();
+        let batch_bytes = batch.iter().map(|e| serde_json::to_vec(e).unwrap().len() as u64 + 1).sum::<u64>();
+
+        let batches = 16 * 1024 / batch_bytes * 3;
+        for n in 0..batches as i64 {
+            let events: Vec<Event> = (0..10).map(|i| spool_event(n * 10 + i)).collect();
+            spool.push(&events).unwrap();
+        }
+
+        assert!(spool.bytes() <= 16 * 1024);
+        assert!(spool.evicted() > 0);
+        assert_eq!(spool.len() + spool.evicted(), batches * 10);
+
+        // What survives is the newest, contiguous tail
+        let remaining = drain(&mut spool);
+        let first = remaining[0];
+        assert_eq!(remaining, (first..batches as i64 * 10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_reopen_resumes_existing_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut spool = DiskSpool::open(dir.path(), 1024 * 1024).unwrap();
+            spool.push(&[spool_event(1), spool_event(2)]).unwrap();
+        }
+
+        let mut spool = DiskSpool::open(dir.path(), 1024 * 1024).unwrap();
+        assert_eq!(spool.len(), 2);
+        spool.push(&[spool_event(3)]).unwrap();
+        assert_eq!(drain(&mut spool), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_push_during_replay_keeps_new_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = DiskSpool::open(dir.path(), 1024 * 1024).unwrap();
+        spool.push(&[spool_event(1), spool_event(2)]).unwrap();
+
+        // Events spooled while a segment is in flight must not go with it
+        let (id, events) = spool.peek_oldest().unwrap().unwrap();
+        assert_eq!(events.len(), 2);
+        spool.push(&[spool_event(3)]).unwrap();
+        spool.remove(id);
+
+        assert_eq!(spool.len(), 1);
+        assert_eq!(drain(&mut spool), vec![3]);
+    }
+}
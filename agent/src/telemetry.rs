@@ -2,15 +2,47 @@
 // Batches events and maintains persistent connection for high throughput.
 
 use anyhow::{Result, Context};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
-use tracing::{info, error, debug};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+use tracing::{info, warn, debug};
 use serde::{Serialize, Deserialize};
 
 use crate::config::AgentConfig;
+use crate::health::{ComponentState, HEALTH};
+use crate::metrics::METRICS;
+use crate::proto;
+use crate::proto::telemetry_service_client::TelemetryServiceClient;
+use crate::ratelimit::RateLimiter;
+use crate::spool::DiskSpool;
+
+/// Delay before the first reconnect attempt after a stream failure.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Upper bound for the exponential reconnect backoff.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Capacity of the outbound gRPC stream buffer.
+const STREAM_BUFFER_SIZE: usize = 1024;
+
+/// How long to wait for in-flight events to flush when the agent shuts down.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Partial batches are flushed at least this often so quiet agents don't sit on events.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Minimum time between "channel full" warnings from one producer.
+const DROP_LOG_INTERVAL: Duration = Duration::from_secs(10);
 
 /// Event types matching the protobuf enum
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum EventType {
     Unspecified = 0,
     ProcessStart = 1,
@@ -24,8 +56,47 @@ pub enum EventType {
     Authentication = 9,
 }
 
-/// Internal event representation (will be converted to protobuf)
-#[derive(Debug, Clone)]
+impl EventType {
+    /// snake_case name used in configuration.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventType::Unspecified => "unspecified",
+            EventType::ProcessStart => "process_start",
+            EventType::ProcessTerminate => "process_terminate",
+            EventType::FileAccess => "file_access",
+            EventType::FileModify => "file_modify",
+            EventType::FileDelete => "file_delete",
+            EventType::NetworkConn => "network_conn",
+            EventType::RegistryModify => "registry_modify",
+            EventType::DlpViolation => "dlp_violation",
+            EventType::Authentication => "authentication",
+        }
+    }
+}
+
+impl std::str::FromStr for EventType {
+    type Err = anyhow::Error;
+
+    /// Accepts the snake_case name or the variant name, case-insensitively.
+    fn from_str(s: &str) -> Result<Self> {
+        let normalized = s.replace('_', "").to_ascii_lowercase();
+        match normalized.as_str() {
+            "processstart" => Ok(EventType::ProcessStart),
+            "processterminate" => Ok(EventType::ProcessTerminate),
+            "fileaccess" => Ok(EventType::FileAccess),
+            "filemodify" => Ok(EventType::FileModify),
+            "filedelete" => Ok(EventType::FileDelete),
+            "networkconn" => Ok(EventType::NetworkConn),
+            "registrymodify" => Ok(EventType::RegistryModify),
+            "dlpviolation" => Ok(EventType::DlpViolation),
+            "authentication" => Ok(EventType::Authentication),
+            _ => anyhow::bail!("Unknown event type: {}", s),
+        }
+    }
+}
+
+/// Internal event representation (converted to protobuf by `crate::proto`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
     pub agent_id: String,
     pub timestamp: i64,
@@ -39,66 +110,1410 @@ pub struct Event {
     pub os_type: String,
 }
 
+/// Source of event timestamps. Production code uses `SystemClock`; tests
+/// pass a `MockClock` to `Event::new_with_clock` for deterministic times.
+pub trait Clock: Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> i64;
+}
+
+/// Wall-clock time from `SystemTime`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64
+    }
+}
+
+/// Clock that stands still until moved with `advance` or `set`.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    millis: AtomicI64,
+}
+
+impl MockClock {
+    pub fn new(start_millis: i64) -> Self {
+        Self { millis: AtomicI64::new(start_millis) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.millis.fetch_add(by.as_millis() as i64, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, millis: i64) {
+        self.millis.store(millis, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_millis(&self) -> i64 {
+        self.millis.load(Ordering::Relaxed)
+    }
+}
+
+/// Host and agent identity stamped on every event.
+/// Resolved once at startup so building an event costs a few string clones
+/// instead of a hostname lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventContext {
+    pub agent_id: String,
+    pub tenant_id: String,
+    pub hostname: String,
+    pub os_type: String,
+}
+
+impl EventContext {
+    /// Look up this machine's hostname and OS for the given agent and tenant.
+    pub fn new(agent_id: String, tenant_id: String) -> Self {
+        Self {
+            agent_id,
+            tenant_id,
+            hostname: hostname::get()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            os_type: std::env::consts::OS.to_string(),
+        }
+    }
+
+    pub fn from_config(config: &AgentConfig) -> Self {
+        Self::new(config.agent_id.clone(), config.tenant_id.clone())
+    }
+}
+
 impl Event {
+    /// Build an event timestamped with the current wall-clock time.
     pub fn new(
-        agent_id: String,
+        context: &EventContext,
         event_type: EventType,
         mitre_tactic: String,
         payload: String,
     ) -> Self {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as i64;
+        Self::new_with_clock(context, event_type, mitre_tactic, payload, &SystemClock)
+    }
 
+    /// Like `new`, taking the timestamp from `clock`.
+    pub fn new_with_clock(
+        context: &EventContext,
+        event_type: EventType,
+        mitre_tactic: String,
+        payload: String,
+        clock: &dyn Clock,
+    ) -> Self {
         Self {
-            agent_id,
-            timestamp,
+            agent_id: context.agent_id.clone(),
+            timestamp: clock.now_millis(),
             event_type,
             mitre_tactic,
             mitre_technique: String::new(),
             severity: 1,
             payload,
-            tenant_id: String::new(),
-            hostname: hostname::get()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            os_type: std::env::consts::OS.to_string(),
+            tenant_id: context.tenant_id.clone(),
+            hostname: context.hostname.clone(),
+            os_type: context.os_type.clone(),
         }
     }
 }
 
+/// Producer end of the telemetry channel used by every collector.
+///
+/// Collectors run inside kernel/ETW callbacks that must never stall, so
+/// `emit_event` never waits: when the channel is full the new event is
+/// dropped and counted, and a warning is logged at most once per
+/// `DROP_LOG_INTERVAL`. An optional rate limiter samples noisy event types
+/// before they reach the channel.
+pub struct EventEmitter {
+    tx: mpsc::Sender<Event>,
+    limiter: Option<Arc<RateLimiter>>,
+    /// Collector name used in log messages.
+    source: &'static str,
+    dropped: AtomicU64,
+    created: Instant,
+    /// Milliseconds after `created` of the last drop warning, plus one (0 = never).
+    last_warning: AtomicU64,
+}
+
+impl EventEmitter {
+    pub fn new(tx: mpsc::Sender<Event>, source: &'static str) -> Self {
+        Self {
+            tx,
+            limiter: None,
+            source,
+            dropped: AtomicU64::new(0),
+            created: Instant::now(),
+            last_warning: AtomicU64::new(0),
+        }
+    }
+
+    /// Sample events from this emitter through `limiter`. Emitters sharing
+    /// one limiter draw from the same buckets, so its limits are agent-wide.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Queue an event without blocking. Returns false if it was dropped or rate limited.
+    pub fn emit_event(&self, event: Event) -> bool {
+        METRICS.event_produced();
+        if let Some(limiter) = &self.limiter {
+            if !limiter.allow(&event) {
+                METRICS.event_rate_limited();
+                return false;
+            }
+        }
+        let closed = match self.tx.try_send(event) {
+            Ok(()) => return true,
+            Err(TrySendError::Full(_)) => false,
+            Err(TrySendError::Closed(_)) => true,
+        };
+
+        METRICS.event_dropped();
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        if closed {
+            debug!("Telemetry channel closed, dropped {} event", self.source);
+        } else if self.claim_warning(self.created.elapsed()) {
+            warn!("Telemetry channel full, {} {} events dropped so far", dropped, self.source);
+        }
+        false
+    }
+
+    /// Events dropped since this emitter was created.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Whether a drop warning may be logged `elapsed` after creation.
+    /// Only one caller wins per interval, even across threads.
+    fn claim_warning(&self, elapsed: Duration) -> bool {
+        let now = elapsed.as_millis() as u64 + 1;
+        let last = self.last_warning.load(Ordering::Relaxed);
+        if last != 0 && now - last.min(now) < DROP_LOG_INTERVAL.as_millis() as u64 {
+            return false;
+        }
+        self.last_warning
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+/// Accumulates events into fixed-size batches behind a hard memory cap.
+struct EventBatcher {
+    buffer: VecDeque<Event>,
+    batch_size: usize,
+    max_buffer_size: usize,
+    dropped: u64,
+}
+
+impl EventBatcher {
+    fn new(batch_size: usize, max_buffer_size: usize) -> Self {
+        Self {
+            buffer: VecDeque::with_capacity(batch_size),
+            batch_size: batch_size.max(1),
+            max_buffer_size,
+            dropped: 0,
+        }
+    }
+
+    /// Queue an event. Once `max_buffer_size` events are buffered, new events
+    /// are dropped so a dead ingestor cannot exhaust agent memory.
+    fn push(&mut self, event: Event) {
+        if self.buffer.len() >= self.max_buffer_size {
+            self.dropped += 1;
+            METRICS.event_dropped();
+            // Warn on the first drop and then periodically to avoid log spam
+            if self.dropped == 1 || self.dropped.is_multiple_of(1000) {
+                warn!(
+                    "Telemetry buffer full ({} events), dropped {} event(s) so far",
+                    self.max_buffer_size, self.dropped
+                );
+            }
+            return;
+        }
+        self.buffer.push_back(event);
+    }
+
+    fn has_full_batch(&self) -> bool {
+        self.buffer.len() >= self.batch_size
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Remove up to `batch_size` events from the front of the buffer.
+    fn next_batch(&mut self) -> Vec<Event> {
+        let n = self.buffer.len().min(self.batch_size);
+        self.buffer.drain(..n).collect()
+    }
+
+    /// Put unsent events back at the front, preserving their order.
+    fn requeue_front(&mut self, events: Vec<Event>) {
+        for event in events.into_iter().rev() {
+            self.buffer.push_front(event);
+        }
+    }
+}
+
+/// How a streaming session ended.
+enum SessionEnd {
+    /// The event channel closed and all buffered events were flushed.
+    ChannelClosed,
+    /// The gRPC stream failed or was closed by the server.
+    StreamLost(anyhow::Error),
+}
+
+/// Events written to one stream and not yet acknowledged.
+///
+/// The ingestor acks every event in stream order, so the running ack count
+/// published by the RPC task tells how many of the oldest written events are
+/// safely stored. Events only count as sent once acked.
+struct AckTracker {
+    acked: watch::Receiver<u64>,
+    /// Events written to the stream so far.
+    written: u64,
+    /// Acks already applied.
+    settled: u64,
+    /// Batched events awaiting their ack, oldest first. Replayed spool
+    /// events are not held here; they stay on disk until acked.
+    pending: VecDeque<Event>,
+}
+
+impl AckTracker {
+    fn new(acked: watch::Receiver<u64>) -> Self {
+        Self { acked, written: 0, settled: 0, pending: VecDeque::new() }
+    }
+
+    /// Record `count` spooled events written to the stream.
+    fn wrote_spooled(&mut self, count: usize) {
+        self.written += count as u64;
+    }
+
+    /// Record a batched event written to the stream.
+    fn wrote(&mut self, event: Event) {
+        self.written += 1;
+        self.pending.push_back(event);
+    }
+
+    /// Apply the acks received so far.
+    fn settle(&mut self) {
+        let acked = (*self.acked.borrow_and_update()).min(self.written);
+        let newly = acked - self.settled;
+        if newly == 0 {
+            return;
+        }
+        self.settled = acked;
+        METRICS.events_sent(newly);
+
+        // `pending` holds the newest written events; release those now acked
+        let unsettled = (self.written - self.settled) as usize;
+        let release = self.pending.len().saturating_sub(unsettled);
+        self.pending.drain(..release);
+    }
+
+    /// Wait until everything written so far is acked.
+    /// Fails if the stream ends first.
+    async fn wait_all(&mut self) -> Result<()> {
+        loop {
+            self.settle();
+            if self.settled == self.written {
+                return Ok(());
+            }
+            self.acked.changed().await.context("Telemetry stream closed before all events were acknowledged")?;
+        }
+    }
+
+    /// Batched events that were written but never acked, oldest first.
+    fn into_unacked(mut self) -> Vec<Event> {
+        self.settle();
+        self.pending.into()
+    }
+}
+
 pub struct TelemetryClient {
     config: AgentConfig,
+    endpoint: Endpoint,
 }
 
 impl TelemetryClient {
+    /// Prepare the ingestor endpoint. With `tls_enabled` the CA certificate
+    /// (and client identity for mTLS) is read here, so a missing or invalid
+    /// file fails startup instead of every reconnect attempt.
     pub async fn new(config: AgentConfig) -> Result<Self> {
-        Ok(Self { config })
+        let mut endpoint = Endpoint::from_shared(config.ingestor_url.clone())
+            .with_context(|| format!("Invalid ingestor URL: {}", config.ingestor_url))?;
+
+        if config.tls_enabled {
+            endpoint = endpoint
+                .tls_config(Self::tls_config(&config)?)
+                .context("Invalid TLS configuration for ingestor connection")?;
+        }
+
+        Ok(Self { config, endpoint })
+    }
+
+    fn tls_config(config: &AgentConfig) -> Result<ClientTlsConfig> {
+        let ca_path = config.tls_ca_cert.as_ref().context("tls_enabled requires tls_ca_cert")?;
+        let mut tls = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(read_pem(ca_path, "CA certificate")?));
+
+        match (&config.tls_client_cert, &config.tls_client_key) {
+            (Some(cert), Some(key)) => {
+                let identity = Identity::from_pem(read_pem(cert, "client certificate")?, read_pem(key, "client key")?);
+                tls = tls.identity(identity);
+            }
+            (None, None) => {}
+            _ => anyhow::bail!("tls_client_cert and tls_client_key must be set together"),
+        }
+
+        Ok(tls)
+    }
+
+    /// Whether the ingestor connection uses TLS.
+    pub fn tls_enabled(&self) -> bool {
+        self.config.tls_enabled
     }
 
     /// Run the telemetry client, receiving events from the channel and streaming to ingestor.
-    /// TODO: Implement actual gRPC streaming (requires generated protobuf code).
-    pub async fn run(self, mut event_rx: mpsc::Receiver<Event>) -> Result<()> {
-        info!("Telemetry client connected to: {}", self.config.ingestor_url);
+    /// Events are sent in batches of `config.batch_size`, with partial batches
+    /// flushed every second. Reconnects with exponential backoff whenever the
+    /// stream fails; returns once the event channel is closed and remaining
+    /// events have been flushed.
+    ///
+    /// With `spool_max_bytes` set, events buffered while offline are written
+    /// to a disk spool and replayed in order ahead of newer events once a
+    /// connection is re-established. If the channel closes while offline the
+    /// client returns and leaves the spool for the next start.
+    ///
+    /// Events are kept (in memory or in the spool) until the ingestor acks
+    /// them, so a stream lost mid-transfer loses nothing; events in flight
+    /// at that moment may be delivered twice.
+    ///
+    /// Events below `min_severity` are counted and discarded on arrival.
+    /// The connection state is published to `HEALTH` for `/healthz`.
+    pub async fn run(self, event_rx: mpsc::Receiver<Event>) -> Result<()> {
+        HEALTH.set_telemetry(ComponentState::Starting);
+        let result = self.run_sessions(event_rx).await;
+        HEALTH.set_telemetry(ComponentState::Stopped);
+        result
+    }
+
+    async fn run_sessions(&self, mut event_rx: mpsc::Receiver<Event>) -> Result<()> {
+        let mut batcher = EventBatcher::new(self.config.batch_size, self.config.max_buffer_size);
+        let mut spool = self.open_spool();
+        let mut channel_open = true;
+        let mut backoff = INITIAL_BACKOFF;
+
+        loop {
+            match self.connect().await {
+                Ok(client) => {
+                    info!("Telemetry client connected to: {}", self.config.ingestor_url);
+                    HEALTH.set_telemetry(ComponentState::Running);
+                    backoff = INITIAL_BACKOFF;
+
+                    match self.stream_session(client, &mut event_rx, &mut batcher, &mut spool, &mut channel_open).await {
+                        SessionEnd::ChannelClosed => {
+                            info!("Event channel closed, telemetry client exiting");
+                            return Ok(());
+                        }
+                        SessionEnd::StreamLost(e) => {
+                            warn!("Telemetry stream lost: {:#}", e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to connect to ingestor {}: {:#}", self.config.ingestor_url, e);
+                }
+            }
+            HEALTH.set_telemetry(ComponentState::Failed);
+
+            // Park buffered events on disk while the ingestor is unreachable
+            Self::spool_buffered(&mut spool, &mut batcher);
+            if let Some(disk) = spool.as_ref().filter(|_| !channel_open) {
+                info!("Event channel closed while offline, {} event(s) left in spool", disk.len());
+                return Ok(());
+            }
+
+            debug!("Reconnecting to ingestor in {:?}", backoff);
+            // Keep draining the channel while offline so producers never stall
+            let reconnect = tokio::time::sleep(backoff);
+            tokio::pin!(reconnect);
+            while channel_open {
+                tokio::select! {
+                    _ = &mut reconnect => break,
+                    received = event_rx.recv() => match received {
+                        Some(event) => {
+                            if !self.meets_min_severity(&event) {
+                                continue;
+                            }
+                            batcher.push(event);
+                            if batcher.has_full_batch() {
+                                Self::spool_buffered(&mut spool, &mut batcher);
+                            }
+                        }
+                        None => channel_open = false,
+                    },
+                }
+            }
+            reconnect.await;
+            Self::spool_buffered(&mut spool, &mut batcher);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Whether `event` is severe enough to send; counts it when it is not.
+    fn meets_min_severity(&self, event: &Event) -> bool {
+        if event.severity >= i32::from(self.config.min_severity) {
+            return true;
+        }
+        METRICS.event_filtered();
+        false
+    }
+
+    /// Open the offline spool if one is configured. A spool that cannot be
+    /// opened is reported and disabled rather than stopping telemetry.
+    fn open_spool(&self) -> Option<DiskSpool> {
+        if self.config.spool_max_bytes == 0 {
+            return None;
+        }
+
+        let dir = self.config.state_dir.join("spool");
+        match DiskSpool::open(&dir, self.config.spool_max_bytes) {
+            Ok(spool) => Some(spool),
+            Err(e) => {
+                warn!("Offline spool disabled: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Move everything in the batcher to the spool, keeping order.
+    /// Without a spool (or if writing fails) events stay in memory.
+    fn spool_buffered(spool: &mut Option<DiskSpool>, batcher: &mut EventBatcher) {
+        if let Some(disk) = spool.as_mut() {
+            Self::spool_batches(disk, batcher);
+        }
+    }
+
+    fn spool_batches(disk: &mut DiskSpool, batcher: &mut EventBatcher) {
+        while !batcher.is_empty() {
+            let batch = batcher.next_batch();
+            if let Err(e) = disk.push(&batch) {
+                warn!("Failed to spool events, keeping them in memory: {:#}", e);
+                batcher.requeue_front(batch);
+                return;
+            }
+        }
+    }
+
+    /// Stream every spooled event, oldest segment first. A segment is
+    /// deleted once the ingestor has acked all its events; one interrupted
+    /// by a stream failure stays on disk and is replayed in full next time.
+    ///
+    /// New events keep being accepted meanwhile so producers never stall
+    /// behind a long replay; full batches of them are spooled behind the
+    /// replayed ones.
+    async fn replay_spool(
+        &self,
+        stream_tx: &mpsc::Sender<proto::Event>,
+        spool: &mut DiskSpool,
+        tracker: &mut AckTracker,
+        event_rx: &mut mpsc::Receiver<Event>,
+        batcher: &mut EventBatcher,
+        channel_open: &mut bool,
+    ) -> Result<()> {
+        if spool.is_empty() {
+            return Ok(());
+        }
+        info!("Replaying {} spooled event(s)", spool.len());
+
+        loop {
+            let (id, events) = match spool.peek_oldest() {
+                Ok(Some(segment)) => segment,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    // An unreadable segment would otherwise block replay forever
+                    warn!("Discarding unreadable spool segment: {:#}", e);
+                    spool.pop_oldest();
+                    continue;
+                }
+            };
+
+            let delivered = async {
+                for event in &events {
+                    if stream_tx.send(proto::Event::from(event)).await.is_err() {
+                        return Err(anyhow::anyhow!("Telemetry stream closed"));
+                    }
+                }
+                tracker.wrote_spooled(events.len());
+                tracker.wait_all().await
+            };
+            tokio::pin!(delivered);
+
+            loop {
+                tokio::select! {
+                    result = &mut delivered => {
+                        result?;
+                        break;
+                    }
+                    received = event_rx.recv(), if *channel_open => match received {
+                        Some(event) => {
+                            if !self.meets_min_severity(&event) {
+                                continue;
+                            }
+                            batcher.push(event);
+                            if batcher.has_full_batch() {
+                                Self::spool_batches(spool, batcher);
+                            }
+                        }
+                        None => *channel_open = false,
+                    },
+                }
+            }
+            spool.remove(id);
+        }
+    }
+
+    /// Dial the ingestor endpoint.
+    async fn connect(&self) -> Result<TelemetryServiceClient<Channel>> {
+        let channel = self.endpoint.clone().connect().await.context("gRPC connect failed")?;
+        Ok(TelemetryServiceClient::new(channel))
+    }
+
+    /// Forward batched events over one StreamEvents RPC until either side goes away.
+    async fn stream_session(
+        &self,
+        mut client: TelemetryServiceClient<Channel>,
+        event_rx: &mut mpsc::Receiver<Event>,
+        batcher: &mut EventBatcher,
+        spool: &mut Option<DiskSpool>,
+        channel_open: &mut bool,
+    ) -> SessionEnd {
+        let (stream_tx, stream_rx) = mpsc::channel::<proto::Event>(STREAM_BUFFER_SIZE);
+        let (acked_tx, acked_rx) = watch::channel(0u64);
+        let mut tracker = AckTracker::new(acked_rx);
+
+        let mut call: JoinHandle<Result<()>> = tokio::spawn(async move {
+            let mut acks = client
+                .stream_events(ReceiverStream::new(stream_rx))
+                .await
+                .context("StreamEvents RPC rejected")?
+                .into_inner();
+
+            while let Some(ack) = acks.message().await.context("Ack stream error")? {
+                if !ack.success {
+                    warn!("Ingestor rejected event: {}", ack.error_message);
+                }
+                acked_tx.send_modify(|acked| *acked += 1);
+            }
 
-        // TODO: Establish gRPC stream using tonic
-        // let mut client = telemetry_client::TelemetryServiceClient::connect(self.config.ingestor_url).await?;
-        // let stream = client.stream_events(...).await?;
+            Ok(())
+        });
 
-        while let Some(event) = event_rx.recv().await {
-            debug!(
-                "Received event: type={:?}, mitre={}, payload_len={}",
-                event.event_type,
-                event.mitre_tactic,
-                event.payload.len()
-            );
+        // Spooled events predate anything in the batcher, so they go first
+        if let Some(disk) = spool.as_mut() {
+            if self.replay_spool(&stream_tx, disk, &mut tracker, event_rx, batcher, channel_open).await.is_err() {
+                return Self::stream_lost(call.await, tracker, batcher);
+            }
+        }
+
+        let mut flush_timer = tokio::time::interval(FLUSH_INTERVAL);
+        flush_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            // Send every complete batch (there may be a backlog after reconnecting)
+            while batcher.has_full_batch() {
+                if Self::flush_batch(&stream_tx, batcher, &mut tracker).await.is_err() {
+                    return Self::stream_lost(call.await, tracker, batcher);
+                }
+            }
+
+            if !*channel_open {
+                while !batcher.is_empty() {
+                    if Self::flush_batch(&stream_tx, batcher, &mut tracker).await.is_err() {
+                        return Self::stream_lost(call.await, tracker, batcher);
+                    }
+                }
+
+                // Close our half of the stream and let queued events drain
+                drop(stream_tx);
+                match tokio::time::timeout(FLUSH_TIMEOUT, &mut call).await {
+                    Ok(Ok(Err(e))) => warn!("Telemetry stream error during flush: {:#}", e),
+                    Err(_) => warn!("Timed out flushing telemetry stream"),
+                    _ => {}
+                }
+
+                let unacked = tracker.into_unacked();
+                if !unacked.is_empty() {
+                    let error = anyhow::anyhow!("{} event(s) not acknowledged before the stream closed", unacked.len());
+                    batcher.requeue_front(unacked);
+                    return SessionEnd::StreamLost(error);
+                }
+                return SessionEnd::ChannelClosed;
+            }
+
+            tokio::select! {
+                received = event_rx.recv() => match received {
+                    Some(event) => {
+                        if self.meets_min_severity(&event) {
+                            batcher.push(event);
+                        }
+                    }
+                    None => *channel_open = false,
+                },
+                _ = flush_timer.tick() => {
+                    if !batcher.is_empty() && Self::flush_batch(&stream_tx, batcher, &mut tracker).await.is_err() {
+                        return Self::stream_lost(call.await, tracker, batcher);
+                    }
+                }
+                Ok(()) = tracker.acked.changed() => tracker.settle(),
+                finished = &mut call => return Self::stream_lost(finished, tracker, batcher),
+            }
+        }
+    }
+
+    /// End a session whose stream failed. Events written but never acked go
+    /// back to the front of the batcher, ahead of anything not yet sent.
+    fn stream_lost(
+        finished: std::result::Result<Result<()>, tokio::task::JoinError>,
+        tracker: AckTracker,
+        batcher: &mut EventBatcher,
+    ) -> SessionEnd {
+        batcher.requeue_front(tracker.into_unacked());
+        SessionEnd::StreamLost(session_error(finished))
+    }
 
-            // TODO: Send event via gRPC stream
-            // stream.send(event).await?;
+    /// Write the next batch to the stream. Written events stay in `tracker`
+    /// until acked; on failure the unsent ones are put back into the batcher.
+    async fn flush_batch(
+        stream_tx: &mpsc::Sender<proto::Event>,
+        batcher: &mut EventBatcher,
+        tracker: &mut AckTracker,
+    ) -> Result<()> {
+        let mut batch = batcher.next_batch().into_iter();
+        debug!("Flushing batch of {} event(s)", batch.len());
+
+        while let Some(event) = batch.next() {
+            if stream_tx.send(proto::Event::from(&event)).await.is_err() {
+                batcher.requeue_front(std::iter::once(event).chain(batch).collect());
+                return Err(anyhow::anyhow!("Telemetry stream closed"));
+            }
+            tracker.wrote(event);
         }
 
         Ok(())
     }
 }
+
+/// Read a PEM file named in the TLS settings.
+fn read_pem(path: &std::path::Path, what: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read TLS {}: {}", what, path.display()))
+}
+
+/// Turn the outcome of a finished RPC task into the reason the session ended.
+fn session_error(finished: std::result::Result<Result<()>, tokio::task::JoinError>) -> anyhow::Error {
+    match finished {
+        Ok(Ok(())) => anyhow::anyhow!("Ingestor closed the stream"),
+        Ok(Err(e)) => e,
+        Err(e) => anyhow::Error::new(e).context("Telemetry stream task failed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dlp::Severity;
+    use crate::proto::telemetry_service_server::{TelemetryService, TelemetryServiceServer};
+    use std::net::SocketAddr;
+    use std::pin::Pin;
+    use tokio_stream::{Stream, StreamExt};
+    use tonic::{Request, Response, Status, Streaming};
+
+    /// In-process ingestor that forwards every received event to a channel.
+    struct MockIngestor {
+        received_tx: mpsc::UnboundedSender<proto::Event>,
+    }
+
+    type AckStream = Pin<Box<dyn Stream<Item = Result<proto::EventAck, Status>> + Send>>;
+
+    // tonic::Status is inherently large; boxing it here would only obscure the mock
+    #[allow(clippy::result_large_err)]
+    #[tonic::async_trait]
+    impl TelemetryService for MockIngestor {
+        type StreamEventsStream = AckStream;
+
+        async fn stream_events(
+            &self,
+            request: Request<Streaming<proto::Event>>,
+        ) -> Result<Response<Self::StreamEventsStream>, Status> {
+            let received_tx = self.received_tx.clone();
+            let acks = request.into_inner().map(move |event| {
+                let event = event?;
+                let _ = received_tx.send(event);
+                Ok(proto::EventAck { success: true, ..Default::default() })
+            });
+            Ok(Response::new(Box::pin(acks)))
+        }
+
+        async fn submit_event(
+            &self,
+            request: Request<proto::Event>,
+        ) -> Result<Response<proto::EventAck>, Status> {
+            let _ = self.received_tx.send(request.into_inner());
+            Ok(Response::new(proto::EventAck { success: true, ..Default::default() }))
+        }
+    }
+
+    /// Ingestor whose first stream fails after `drop_after` events, with the
+    /// last of them received but never acked. Later streams behave normally.
+    struct DroppingIngestor {
+        received_tx: mpsc::UnboundedSender<proto::Event>,
+        streams: std::sync::atomic::AtomicUsize,
+        drop_after: usize,
+    }
+
+    #[allow(clippy::result_large_err)]
+    #[tonic::async_trait]
+    impl TelemetryService for DroppingIngestor {
+        type StreamEventsStream = AckStream;
+
+        async fn stream_events(
+            &self,
+            request: Request<Streaming<proto::Event>>,
+        ) -> Result<Response<Self::StreamEventsStream>, Status> {
+            let received_tx = self.received_tx.clone();
+            let first = self.streams.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0;
+            let drop_after = self.drop_after;
+            let mut seen = 0;
+            let acks = request.into_inner().map(move |event| {
+                let event = event?;
+                let _ = received_tx.send(event);
+                seen += 1;
+                if first && seen == drop_after {
+                    return Err(Status::unavailable("ingestor restarting"));
+                }
+                Ok(proto::EventAck { success: true, ..Default::default() })
+            });
+            Ok(Response::new(Box::pin(acks)))
+        }
+
+        async fn submit_event(
+            &self,
+            _request: Request<proto::Event>,
+        ) -> Result<Response<proto::EventAck>, Status> {
+            Err(Status::unimplemented("stream only"))
+        }
+    }
+
+    /// Ingestor that receives events right away but holds every ack until
+    /// `release` becomes true.
+    struct GatedIngestor {
+        received_tx: mpsc::UnboundedSender<proto::Event>,
+        release: watch::Receiver<bool>,
+    }
+
+    #[allow(clippy::result_large_err)]
+    #[tonic::async_trait]
+    impl TelemetryService for GatedIngestor {
+        type StreamEventsStream = AckStream;
+
+        async fn stream_events(
+            &self,
+            request: Request<Streaming<proto::Event>>,
+        ) -> Result<Response<Self::StreamEventsStream>, Status> {
+            let received_tx = self.received_tx.clone();
+            let mut release = self.release.clone();
+            let (ack_tx, ack_rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+            let mut events = request.into_inner();
+            tokio::spawn(async move {
+                while let Some(Ok(event)) = events.next().await {
+                    let _ = received_tx.send(event);
+                    if release.wait_for(|open| *open).await.is_err() {
+                        return;
+                    }
+                    if ack_tx.send(Ok(proto::EventAck { success: true, ..Default::default() })).await.is_err() {
+                        return;
+                    }
+                }
+            });
+            Ok(Response::new(Box::pin(ReceiverStream::new(ack_rx))))
+        }
+
+        async fn submit_event(
+            &self,
+            _request: Request<proto::Event>,
+        ) -> Result<Response<proto::EventAck>, Status> {
+            Err(Status::unimplemented("stream only"))
+        }
+    }
+
+    fn serve<S: TelemetryService>(listener: tokio::net::TcpListener, service: S) {
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(TelemetryServiceServer::new(service))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+                .await
+                .unwrap();
+        });
+    }
+
+    async fn spawn_ingestor(
+        listener: tokio::net::TcpListener,
+    ) -> mpsc::UnboundedReceiver<proto::Event> {
+        let (received_tx, received_rx) = mpsc::unbounded_channel();
+        serve(listener, MockIngestor { received_tx });
+        received_rx
+    }
+
+    fn tls_fixture(name: &str) -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls").join(name)
+    }
+
+    fn tls_config(addr: SocketAddr) -> AgentConfig {
+        AgentConfig {
+            ingestor_url: format!("https://{}", addr),
+            tls_enabled: true,
+            tls_ca_cert: Some(tls_fixture("ca.pem")),
+            ..test_config(addr)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tls_client_performs_handshake() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let config = AgentConfig {
+            tls_client_cert: Some(tls_fixture("client.pem")),
+            tls_client_key: Some(tls_fixture("client.key")),
+            ..tls_config(addr)
+        };
+        let client = TelemetryClient::new(config).await.unwrap();
+        assert!(client.tls_enabled());
+        let connect = tokio::spawn(async move { client.connect().await.map(|_| ()) });
+
+        // The first bytes on the wire must be a TLS handshake record
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut header = [0u8; 3];
+        tokio::io::AsyncReadExt::read_exact(&mut socket, &mut header).await.unwrap();
+        assert_eq!(header[..2], [0x16, 0x03], "expected a TLS ClientHello, got {:02x?}", header);
+
+        drop(socket);
+        assert!(connect.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tls_missing_cert_files_fail_fast() {
+        let addr: SocketAddr = "127.0.0.1:443".parse().unwrap();
+
+        let config = AgentConfig { tls_ca_cert: Some(tls_fixture("missing-ca.pem")), ..tls_config(addr) };
+        let err = TelemetryClient::new(config).await.err().expect("missing CA must be rejected");
+        assert!(format!("{:#}", err).contains("missing-ca.pem"), "error should name the file: {:#}", err);
+
+        let config = AgentConfig {
+            tls_client_cert: Some(tls_fixture("client.pem")),
+            tls_client_key: Some(tls_fixture("missing.key")),
+            ..tls_config(addr)
+        };
+        assert!(TelemetryClient::new(config).await.is_err());
+
+        assert!(!TelemetryClient::new(test_config(addr)).await.unwrap().tls_enabled());
+    }
+
+    fn test_config(addr: SocketAddr) -> AgentConfig {
+        AgentConfig {
+            agent_id: "agent-1".to_string(),
+            ingestor_url: format!("http://{}", addr),
+            tenant_id: "tenant-1".to_string(),
+            ..AgentConfig::default()
+        }
+    }
+
+    fn test_event(n: i64) -> Event {
+        let mut event = Event::new_with_clock(
+            &test_context(),
+            EventType::DlpViolation,
+            "TA0010_Exfiltration".to_string(),
+            format!(r#"{{"seq":{}}}"#, n),
+            &MockClock::new(1_700_000_000_000 + n),
+        );
+        event.mitre_technique = "T1048".to_string();
+        event.severity = 4;
+        event
+    }
+
+    #[test]
+    fn test_mock_clock_controls_event_timestamps() {
+        let clock = MockClock::new(1_700_000_000_000);
+        let event = |clock: &MockClock| {
+            Event::new_with_clock(&test_context(), EventType::ProcessStart, "TA0002_Execution".to_string(), String::new(), clock)
+        };
+
+        let first = event(&clock);
+        clock.advance(Duration::from_millis(250));
+        let second = event(&clock);
+        assert_eq!(first.timestamp, 1_700_000_000_000);
+        assert_eq!(second.timestamp, 1_700_000_000_250);
+
+        // The clock only moves when told to
+        assert_eq!(event(&clock).timestamp, second.timestamp);
+        clock.set(42);
+        assert_eq!(event(&clock).timestamp, 42);
+    }
+
+    #[tokio::test]
+    async fn test_emit_event_drops_newest_when_full() {
+        let (event_tx, mut event_rx) = mpsc::channel(2);
+        let emitter = EventEmitter::new(event_tx, "test");
+
+        // Nobody is receiving: a blocking send would hang here
+        let emitted: Vec<bool> = (0..5).map(|n| emitter.emit_event(test_event(n))).collect();
+        assert_eq!(emitted, vec![true, true, false, false, false]);
+        assert_eq!(emitter.dropped(), 3);
+
+        // The queued events are the oldest ones
+        assert_eq!(event_rx.recv().await.unwrap().payload, r#"{"seq":0}"#);
+        assert_eq!(event_rx.recv().await.unwrap().payload, r#"{"seq":1}"#);
+
+        drop(event_rx);
+        assert!(!emitter.emit_event(test_event(5)));
+        assert_eq!(emitter.dropped(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_emit_event_applies_rate_limits() {
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let emitter = EventEmitter::new(event_tx, "test")
+            .with_rate_limiter(Arc::new(RateLimiter::new(&"file_access=2".parse().unwrap())));
+
+        let file_access = || {
+            let mut event = Event::new(&test_context(), EventType::FileAccess, "TA0009_Collection".to_string(), String::new());
+            event.severity = 1;
+            event
+        };
+        let emitted: Vec<bool> = (0..4).map(|_| emitter.emit_event(file_access())).collect();
+        assert_eq!(emitted, vec![true, true, false, false]);
+        // Critical events bypass the limit
+        assert!(emitter.emit_event(test_event(0)));
+
+        // Sampled-out events are not channel drops
+        assert_eq!(emitter.dropped(), 0);
+        drop(emitter);
+        let mut received = 0;
+        while event_rx.recv().await.is_some() {
+            received += 1;
+        }
+        assert_eq!(received, 3);
+    }
+
+    #[test]
+    fn test_shared_rate_limiter_spans_emitters() {
+        let (event_tx, _event_rx) = mpsc::channel(16);
+        let limiter = Arc::new(RateLimiter::new(&"file_access=2".parse().unwrap()));
+        let ebpf = EventEmitter::new(event_tx.clone(), "eBPF").with_rate_limiter(limiter.clone());
+        let authlog = EventEmitter::new(event_tx, "auth log").with_rate_limiter(limiter);
+
+        let file_access = || Event::new(&test_context(), EventType::FileAccess, "TA0009_Collection".to_string(), String::new());
+        assert!(ebpf.emit_event(file_access()));
+        assert!(authlog.emit_event(file_access()));
+        // Both emitters have spent the one agent-wide budget
+        assert!(!ebpf.emit_event(file_access()));
+        assert!(!authlog.emit_event(file_access()));
+    }
+
+    #[test]
+    fn test_event_type_names_round_trip() {
+        for event_type in [EventType::FileAccess, EventType::NetworkConn, EventType::DlpViolation, EventType::Authentication] {
+            assert_eq!(event_type.as_str().parse::<EventType>().unwrap(), event_type);
+        }
+        assert_eq!("ProcessStart".parse::<EventType>().unwrap(), EventType::ProcessStart);
+        assert!("unspecified".parse::<EventType>().is_err());
+    }
+
+    #[test]
+    fn test_drop_warnings_are_rate_limited() {
+        let (event_tx, _event_rx) = mpsc::channel(1);
+        let emitter = EventEmitter::new(event_tx, "test");
+
+        assert!(emitter.claim_warning(Duration::ZERO));
+        assert!(!emitter.claim_warning(Duration::from_secs(1)));
+        assert!(!emitter.claim_warning(DROP_LOG_INTERVAL - Duration::from_millis(1)));
+        assert!(emitter.claim_warning(DROP_LOG_INTERVAL));
+        assert!(!emitter.claim_warning(DROP_LOG_INTERVAL + Duration::from_secs(1)));
+    }
+
+    fn test_context() -> EventContext {
+        EventContext {
+            agent_id: "agent-1".to_string(),
+            tenant_id: "tenant-1".to_string(),
+            hostname: "host-1".to_string(),
+            os_type: "linux".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_event_carries_context_identity() {
+        let event = Event::new(&test_context(), EventType::ProcessStart, "TA0002_Execution".to_string(), String::new());
+        assert_eq!(event.agent_id, "agent-1");
+        assert_eq!(event.tenant_id, "tenant-1");
+        // Taken from the context, not looked up again
+        assert_eq!(event.hostname, "host-1");
+        assert_eq!(event.os_type, "linux");
+
+        let context = EventContext::new("agent-2".to_string(), "tenant-2".to_string());
+        assert_eq!(context.tenant_id, "tenant-2");
+        assert_eq!(context.os_type, std::env::consts::OS);
+        assert_eq!(context.hostname, hostname::get().unwrap().to_string_lossy());
+    }
+
+    #[tokio::test]
+    async fn test_run_streams_events_to_ingestor() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut received_rx = spawn_ingestor(listener).await;
+
+        let client = TelemetryClient::new(test_config(addr)).await.unwrap();
+        let (event_tx, event_rx) = mpsc::channel(16);
+        let handle = tokio::spawn(client.run(event_rx));
+
+        let sent: Vec<Event> = (0..3).map(test_event).collect();
+        for event in &sent {
+            event_tx.send(event.clone()).await.unwrap();
+        }
+        drop(event_tx);
+
+        tokio::time::timeout(Duration::from_secs(10), handle)
+            .await
+            .expect("client should exit once the channel closes")
+            .unwrap()
+            .unwrap();
+
+        for event in &sent {
+            let received = received_rx.recv().await.unwrap();
+            assert_eq!(received, proto::Event::from(event));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_drops_events_below_min_severity() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut received_rx = spawn_ingestor(listener).await;
+
+        let config = AgentConfig { min_severity: Severity::High, ..test_config(addr) };
+        let client = TelemetryClient::new(config).await.unwrap();
+        let (event_tx, event_rx) = mpsc::channel(16);
+        let handle = tokio::spawn(client.run(event_rx));
+
+        let mut medium = Event::new(&test_context(), EventType::FileAccess, "TA0009_Collection".to_string(), String::new());
+        medium.severity = Severity::Medium.into();
+        let critical = test_event(1);
+        assert_eq!(critical.severity, i32::from(Severity::Critical));
+        event_tx.send(medium).await.unwrap();
+        event_tx.send(critical.clone()).await.unwrap();
+        drop(event_tx);
+
+        tokio::time::timeout(Duration::from_secs(10), handle).await.unwrap().unwrap().unwrap();
+        assert_eq!(received_rx.recv().await.unwrap(), proto::Event::from(&critical));
+        // The ingestor's sender is still alive, so an empty channel means nothing else arrived
+        assert!(received_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_flushes_in_batch_size_groups() {
+        // Each batched write is logged by flush_batch; capture those lines
+        let logs = crate::logging::Captured::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(logs.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // Start offline so all 250 events are buffered before the stream opens
+        let addr = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap()
+        };
+        let config = AgentConfig { batch_size: 100, ..test_config(addr) };
+        let client = TelemetryClient::new(config).await.unwrap();
+        let (event_tx, event_rx) = mpsc::channel(256);
+        let handle = tokio::spawn(client.run(event_rx));
+        for n in 0..250 {
+            event_tx.send(test_event(n)).await.unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while event_tx.capacity() < event_tx.max_capacity() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("client should buffer events while offline");
+
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        let mut received_rx = spawn_ingestor(listener).await;
+        for n in 0..250 {
+            let received = tokio::time::timeout(Duration::from_secs(10), received_rx.recv())
+                .await
+                .expect("buffered event should be sent")
+                .unwrap();
+            assert_eq!(received, proto::Event::from(&test_event(n)));
+        }
+
+        // The channel is still open, so only the flush timer sends the partial batch
+        let flushes: Vec<usize> = logs
+            .text()
+            .lines()
+            .filter_map(|line| line.split("Flushing batch of ").nth(1)?.split(' ').next()?.parse().ok())
+            .collect();
+        assert_eq!(flushes, vec![100, 100, 50]);
+
+        drop(event_tx);
+        tokio::time::timeout(Duration::from_secs(10), handle).await.unwrap().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_batcher_drops_beyond_max_buffer() {
+        let mut batcher = EventBatcher::new(10, 25);
+        for n in 0..40 {
+            batcher.push(test_event(n));
+        }
+
+        assert_eq!(batcher.buffer.len(), 25);
+        assert_eq!(batcher.dropped, 15);
+    }
+
+    #[test]
+    fn test_batcher_requeue_preserves_order() {
+        let mut batcher = EventBatcher::new(3, 100);
+        for n in 0..5 {
+            batcher.push(test_event(n));
+        }
+
+        let batch = batcher.next_batch();
+        batcher.requeue_front(batch[1..].to_vec());
+
+        let order: Vec<i64> = batcher.buffer.iter().map(|e| e.timestamp - 1_700_000_000_000).collect();
+        assert_eq!(order, vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_run_flushes_partial_batch_on_timer() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut received_rx = spawn_ingestor(listener).await;
+
+        let client = TelemetryClient::new(test_config(addr)).await.unwrap();
+        let (event_tx, event_rx) = mpsc::channel(1000);
+        let handle = tokio::spawn(client.run(event_rx));
+
+        for n in 0..250 {
+            event_tx.send(test_event(n)).await.unwrap();
+        }
+
+        // Channel stays open: the last 50 events only arrive via the flush timer
+        for n in 0..250 {
+            let received = tokio::time::timeout(Duration::from_secs(10), received_rx.recv())
+                .await
+                .expect("all events should be flushed")
+                .unwrap();
+            assert_eq!(received.timestamp, test_event(n).timestamp);
+        }
+
+        drop(event_tx);
+        tokio::time::timeout(Duration::from_secs(10), handle).await.unwrap().unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_reconnects_when_ingestor_starts_late() {
+        // Reserve a port, then release it so the first dial is refused
+        let addr = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap()
+        };
+
+        let client = TelemetryClient::new(test_config(addr)).await.unwrap();
+        let (event_tx, event_rx) = mpsc::channel(16);
+        let handle = tokio::spawn(client.run(event_rx));
+        event_tx.send(test_event(1)).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        let mut received_rx = spawn_ingestor(listener).await;
+
+        let received = tokio::time::timeout(Duration::from_secs(10), received_rx.recv())
+            .await
+            .expect("event should arrive after reconnect")
+            .unwrap();
+        assert_eq!(received, proto::Event::from(&test_event(1)));
+
+        drop(event_tx);
+        tokio::time::timeout(Duration::from_secs(10), handle).await.unwrap().unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_spools_while_offline_and_replays_on_reconnect() {
+        // Reserve a port, then release it so dials are refused until the ingestor starts
+        let addr = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap()
+        };
+        let state_dir = tempfile::tempdir().unwrap();
+        let spool_dir = state_dir.path().join("spool");
+        let spooled_segments = || std::fs::read_dir(&spool_dir).map(|entries| entries.count()).unwrap_or(0);
+
+        let config = AgentConfig {
+            spool_max_bytes: 1024 * 1024,
+            state_dir: state_dir.path().to_path_buf(),
+            ..test_config(addr)
+        };
+        let client = TelemetryClient::new(config).await.unwrap();
+        let (event_tx, event_rx) = mpsc::channel(16);
+        let handle = tokio::spawn(client.run(event_rx));
+        for n in 0..5 {
+            event_tx.send(test_event(n)).await.unwrap();
+        }
+
+        // The first dial fails, so the buffered events are written to disk
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while spooled_segments() == 0 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("events should be spooled while offline");
+
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        let mut received_rx = spawn_ingestor(listener).await;
+        for n in 0..5 {
+            let received = tokio::time::timeout(Duration::from_secs(10), received_rx.recv())
+                .await
+                .expect("spooled event should be replayed after reconnect")
+                .unwrap();
+            assert_eq!(received, proto::Event::from(&test_event(n)));
+        }
+
+        drop(event_tx);
+        tokio::time::timeout(Duration::from_secs(10), handle).await.unwrap().unwrap().unwrap();
+        assert_eq!(spooled_segments(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_spooled_events_survive_stream_dropped_during_replay() {
+        let addr = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap()
+        };
+        let state_dir = tempfile::tempdir().unwrap();
+        let spool_dir = state_dir.path().join("spool");
+        let spooled_segments = || std::fs::read_dir(&spool_dir).map(|entries| entries.count()).unwrap_or(0);
+
+        let config = AgentConfig {
+            spool_max_bytes: 1024 * 1024,
+            state_dir: state_dir.path().to_path_buf(),
+            ..test_config(addr)
+        };
+        let client = TelemetryClient::new(config).await.unwrap();
+        let (event_tx, event_rx) = mpsc::channel(16);
+        let handle = tokio::spawn(client.run(event_rx));
+        for n in 0..5 {
+            event_tx.send(test_event(n)).await.unwrap();
+        }
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while spooled_segments() == 0 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("events should be spooled while offline");
+
+        // The first stream fails on the third replayed event without acking it
+        let (received_tx, mut received_rx) = mpsc::unbounded_channel();
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        serve(listener, DroppingIngestor { received_tx, streams: Default::default(), drop_after: 3 });
+
+        // Everything not acked is replayed by the next session
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(20), async {
+            while !(0..5).all(|n| received.contains(&proto::Event::from(&test_event(n))))
+                || received.iter().filter(|e| **e == proto::Event::from(&test_event(2))).count() < 2
+            {
+                received.push(received_rx.recv().await.unwrap());
+            }
+        })
+        .await
+        .expect("spooled events should be replayed after the stream drops");
+
+        drop(event_tx);
+        tokio::time::timeout(Duration::from_secs(10), handle).await.unwrap().unwrap().unwrap();
+        assert_eq!(spooled_segments(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_live_events_are_accepted_during_replay() {
+        let addr = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap()
+        };
+        let state_dir = tempfile::tempdir().unwrap();
+        let spool_dir = state_dir.path().join("spool");
+        let spooled_segments = || std::fs::read_dir(&spool_dir).map(|entries| entries.count()).unwrap_or(0);
+
+        let config = AgentConfig {
+            batch_size: 5,
+            spool_max_bytes: 1024 * 1024,
+            state_dir: state_dir.path().to_path_buf(),
+            ..test_config(addr)
+        };
+        let client = TelemetryClient::new(config).await.unwrap();
+        // A small channel fills quickly if the client stops reading it
+        let (event_tx, event_rx) = mpsc::channel(4);
+        let handle = tokio::spawn(client.run(event_rx));
+        let emitter = EventEmitter::new(event_tx, "test");
+        for n in 0..5 {
+            assert!(emitter.emit_event(test_event(n)));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while spooled_segments() == 0 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("events should be spooled while offline");
+
+        // Acks are withheld, so the replay stays in flight
+        let (release_tx, release_rx) = watch::channel(false);
+        let (received_tx, mut received_rx) = mpsc::unbounded_channel();
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        serve(listener, GatedIngestor { received_tx, release: release_rx });
+        tokio::time::timeout(Duration::from_secs(10), received_rx.recv())
+            .await
+            .expect("replay should start after reconnect")
+            .unwrap();
+
+        for n in 5..25 {
+            emitter.emit_event(test_event(n));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(emitter.dropped(), 0, "live events were dropped during replay");
+
+        release_tx.send(true).unwrap();
+        drop(emitter);
+        tokio::time::timeout(Duration::from_secs(10), handle).await.unwrap().unwrap().unwrap();
+
+        // Everything arrives once, the spooled backlog first
+        let mut received = vec![proto::Event::from(&test_event(0))];
+        while let Ok(event) = received_rx.try_recv() {
+            received.push(event);
+        }
+        let expected: Vec<proto::Event> = (0..25).map(|n| proto::Event::from(&test_event(n))).collect();
+        assert_eq!(received, expected);
+        assert_eq!(spooled_segments(), 0);
+    }
+}
@@ -0,0 +1,61 @@
+// Integration tests for the `scan` subcommand
+// Runs the agent binary as an offline policy validator.
+
+use sentinel_agent::dlp::DlpEngine;
+use std::process::Command;
+
+#[test]
+fn test_scan_prints_matches_as_json() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let secret = b"CONFIDENTIAL: Q3 acquisition target is Initech, offer 42.50/share, board vote on the 14th. Legal review by Hartley & Co. Do not forward.";
+    let hash = DlpEngine::new().hash_chunk(&secret[..64]);
+    let policy = dir.path().join("policy.json");
+    std::fs::write(
+        &policy,
+        serde_json::json!({
+            "rules": [{ "id": "MERGER-DOCS", "severity": "critical", "hashes": [hash] }]
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let docs = dir.path().join("docs");
+    std::fs::create_dir(&docs).unwrap();
+    std::fs::write(docs.join("memo.txt"), secret).unwrap();
+    std::fs::write(docs.join("lunch.txt"), b"Pizza on Friday, the usual place.").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sentinel-agent"))
+        .arg("scan")
+        .arg("--policy")
+        .arg(&policy)
+        .arg("--path")
+        .arg(&docs)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "scan failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let matches: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let matches = matches.as_array().unwrap();
+    assert_eq!(matches.len(), 1, "unexpected matches: {:?}", matches);
+    assert_eq!(matches[0]["rule_id"], "MERGER-DOCS");
+    assert_eq!(matches[0]["severity"], "critical");
+    assert_eq!(matches[0]["matched_hash"], hash.as_str());
+    assert_eq!(matches[0]["offset"], 0);
+    assert!(matches[0]["path"].as_str().unwrap().ends_with("memo.txt"));
+}
+
+#[test]
+fn test_scan_rejects_missing_policy() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sentinel-agent"))
+        .args(["scan", "--policy"])
+        .arg(dir.path().join("missing.json"))
+        .arg("--path")
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
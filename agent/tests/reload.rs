@@ -0,0 +1,73 @@
+// Hot reload: SIGHUP swaps a changed DLP policy into the running engine
+#![cfg(unix)]
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sentinel_agent::config::AgentConfig;
+use sentinel_agent::dlp::DlpEngine;
+use sentinel_agent::ioc::IocStore;
+use sentinel_agent::reload::{ReloadTrigger, Reloader};
+use tokio_util::sync::CancellationToken;
+
+fn write_policy(path: &Path, rule_id: &str, hashes: &[&str]) {
+    let policy = serde_json::json!({
+        "rules": [{"id": rule_id, "severity": "high", "hashes": hashes}],
+    });
+    std::fs::write(path, policy.to_string()).unwrap();
+}
+
+fn scanned_rules(engine: &DlpEngine, data: &[u8]) -> Vec<String> {
+    engine.scan_buffer(data).into_iter().map(|m| m.rule_id).collect()
+}
+
+#[tokio::test]
+async fn test_sighup_reloads_dlp_policy() {
+    let data: Vec<u8> = (0..256u32).map(|i| (i * 37 % 251) as u8).collect();
+    let engine = Arc::new(DlpEngine::new());
+    let (first, second) = (engine.hash_chunk(&data[..64]), engine.hash_chunk(&data[128..192]));
+
+    let dir = tempfile::tempdir().unwrap();
+    let policy_path = dir.path().join("dlp.json");
+    let config_path = dir.path().join("agent.toml");
+    write_policy(&policy_path, "OLD", &[&first]);
+    std::fs::write(
+        &config_path,
+        format!("agent_id = \"agent-reload\"\ndlp_policy = {:?}\n", policy_path.to_str().unwrap()),
+    )
+    .unwrap();
+
+    let config = AgentConfig::from_file(&config_path).unwrap();
+    engine.load_fingerprints_from_policy(policy_path.to_str().unwrap()).unwrap();
+    assert_eq!(scanned_rules(&engine, &data), vec!["OLD"]);
+
+    let reloader = Reloader::new(
+        config,
+        move || AgentConfig::from_file(&config_path),
+        engine.clone(),
+        Arc::new(IocStore::new()),
+    );
+    let trigger = ReloadTrigger::install().unwrap();
+    let shutdown = CancellationToken::new();
+    let handle = tokio::spawn(reloader.run(trigger, shutdown.clone()));
+
+    write_policy(&policy_path, "NEW", &[&first, &second]);
+    let status = std::process::Command::new("kill")
+        .args(["-HUP", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while engine.fingerprint_count() != 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("policy was not reloaded");
+    assert_eq!(scanned_rules(&engine, &data), vec!["NEW", "NEW"]);
+
+    shutdown.cancel();
+    handle.await.unwrap();
+}